@@ -0,0 +1,188 @@
+use std::str::FromStr;
+
+use crate::core::{
+    container::{Direction, Mode, Orientation},
+    layout::MAX_GAP,
+};
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    FocusNext,
+    FocusPrev,
+    Close,
+    Kill,
+    Spawn(String),
+    NextLayout,
+    PrevLayout,
+    Reload,
+    Status,
+    Workspace(usize),
+    MoveToWorkspace(usize, bool),
+    FocusNextMonitor,
+    FocusPrevMonitor,
+    MoveToNextMonitor,
+    MoveToPrevMonitor,
+    GrowMaster,
+    ShrinkMaster,
+    IncMaster,
+    DecMaster,
+    Zoom,
+    ToggleFloating,
+    IncGaps,
+    DecGaps,
+    IncOuterGaps,
+    DecOuterGaps,
+    SetGaps(u32, u32),
+    ToggleSmartGaps,
+    Preselect(Option<Direction>),
+    GrowSplit,
+    ShrinkSplit,
+    Rotate,
+    Flip(Orientation),
+    ContainerMode(Mode),
+}
+
+// Actions are written as `name [argument]`, e.g. `focus-next` or `spawn xterm -e htop`.
+// Workspaces are numbered from 1.
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = match s.find(char::is_whitespace) {
+            Some(i) => (&s[..i], s[i..].trim()),
+            None => (s, ""),
+        };
+
+        match (name, arg) {
+            ("focus-next", "") => Ok(Action::FocusNext),
+            ("focus-prev", "") => Ok(Action::FocusPrev),
+            ("close", "") => Ok(Action::Close),
+            ("kill", "") => Ok(Action::Kill),
+            ("spawn", "") => Err("action 'spawn' needs a command".to_string()),
+            ("spawn", command) => Ok(Action::Spawn(command.to_string())),
+            ("next-layout", "") => Ok(Action::NextLayout),
+            ("prev-layout", "") => Ok(Action::PrevLayout),
+            ("reload", "") => Ok(Action::Reload),
+            ("status", "") => Ok(Action::Status),
+            ("workspace", n) => Ok(Action::Workspace(workspace_index(n)?)),
+            ("move-to-workspace", arg) => match arg.split_whitespace().collect::<Vec<_>>()[..] {
+                [n] => Ok(Action::MoveToWorkspace(workspace_index(n)?, false)),
+                [n, "follow"] => Ok(Action::MoveToWorkspace(workspace_index(n)?, true)),
+                _ => Err("usage: move-to-workspace <n> [follow]".to_string()),
+            },
+            ("focus-next-monitor", "") => Ok(Action::FocusNextMonitor),
+            ("focus-prev-monitor", "") => Ok(Action::FocusPrevMonitor),
+            ("move-to-next-monitor", "") => Ok(Action::MoveToNextMonitor),
+            ("move-to-prev-monitor", "") => Ok(Action::MoveToPrevMonitor),
+            ("grow-master", "") => Ok(Action::GrowMaster),
+            ("shrink-master", "") => Ok(Action::ShrinkMaster),
+            ("inc-master", "") => Ok(Action::IncMaster),
+            ("dec-master", "") => Ok(Action::DecMaster),
+            ("zoom", "") => Ok(Action::Zoom),
+            ("toggle-floating", "") => Ok(Action::ToggleFloating),
+            ("inc-gaps", "") => Ok(Action::IncGaps),
+            ("dec-gaps", "") => Ok(Action::DecGaps),
+            ("inc-outer-gaps", "") => Ok(Action::IncOuterGaps),
+            ("dec-outer-gaps", "") => Ok(Action::DecOuterGaps),
+            ("set-gaps", arg) => match arg.split_whitespace().collect::<Vec<_>>()[..] {
+                [inner, outer] => match (inner.parse(), outer.parse()) {
+                    (Ok(inner), Ok(outer)) if inner <= MAX_GAP && outer <= MAX_GAP => {
+                        Ok(Action::SetGaps(inner, outer))
+                    }
+                    (Ok(_), Ok(_)) => Err(format!("gaps must be at most {} pixels", MAX_GAP)),
+                    _ => Err(format!("invalid gaps '{}'", arg)),
+                },
+                _ => Err("usage: set-gaps <inner> <outer>".to_string()),
+            },
+            ("toggle-smart-gaps", "") => Ok(Action::ToggleSmartGaps),
+            ("preselect", "left") => Ok(Action::Preselect(Some(Direction::Left))),
+            ("preselect", "right") => Ok(Action::Preselect(Some(Direction::Right))),
+            ("preselect", "up") => Ok(Action::Preselect(Some(Direction::Up))),
+            ("preselect", "down") => Ok(Action::Preselect(Some(Direction::Down))),
+            ("preselect", "cancel") => Ok(Action::Preselect(None)),
+            ("preselect", _) => Err("usage: preselect left|right|up|down|cancel".to_string()),
+            ("grow-split", "") => Ok(Action::GrowSplit),
+            ("shrink-split", "") => Ok(Action::ShrinkSplit),
+            ("rotate", "") => Ok(Action::Rotate),
+            ("flip", "horizontal") => Ok(Action::Flip(Orientation::Horizontal)),
+            ("flip", "vertical") => Ok(Action::Flip(Orientation::Vertical)),
+            ("flip", _) => Err("usage: flip horizontal|vertical".to_string()),
+            ("container-mode", "split") => Ok(Action::ContainerMode(Mode::Split)),
+            ("container-mode", "tabbed") => Ok(Action::ContainerMode(Mode::Tabbed)),
+            ("container-mode", "stacked") => Ok(Action::ContainerMode(Mode::Stacked)),
+            ("container-mode", _) => Err("usage: container-mode split|tabbed|stacked".to_string()),
+            _ => Err(format!("unknown action '{}'", s)),
+        }
+    }
+}
+
+fn workspace_index(n: &str) -> Result<usize, String> {
+    match n.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n - 1),
+        _ => Err(format!("invalid workspace number '{}'", n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Action {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_names_and_arguments() {
+        assert!(matches!(parse("focus-next"), Action::FocusNext));
+        assert!(matches!(parse("  close  "), Action::Close));
+        assert!(matches!(parse("kill"), Action::Kill));
+        assert!(matches!(parse("  zoom  "), Action::Zoom));
+        assert!(
+            matches!(parse("spawn dmenu_run -l 10"), Action::Spawn(c) if c == "dmenu_run -l 10")
+        );
+        assert!(matches!(parse("prev-layout"), Action::PrevLayout));
+        assert!(matches!(parse("grow-master"), Action::GrowMaster));
+        assert!(matches!(parse("workspace 3"), Action::Workspace(2)));
+        assert!(matches!(
+            parse("move-to-workspace 1"),
+            Action::MoveToWorkspace(0, false)
+        ));
+        assert!(matches!(
+            parse("move-to-workspace 9 follow"),
+            Action::MoveToWorkspace(8, true)
+        ));
+        assert!(matches!(parse("set-gaps 4 8"), Action::SetGaps(4, 8)));
+        assert!(matches!(parse("preselect cancel"), Action::Preselect(None)));
+        assert!(matches!(
+            parse("preselect left"),
+            Action::Preselect(Some(Direction::Left))
+        ));
+        assert!(matches!(
+            parse("flip vertical"),
+            Action::Flip(Orientation::Vertical)
+        ));
+        assert!(matches!(
+            parse("container-mode stacked"),
+            Action::ContainerMode(Mode::Stacked)
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_names_and_arguments() {
+        let err = |s: &str| s.parse::<Action>().unwrap_err();
+        assert_eq!(err("focus_next"), "unknown action 'focus_next'");
+        assert_eq!(err("close now"), "unknown action 'close now'");
+        assert_eq!(err("spawn"), "action 'spawn' needs a command");
+        assert_eq!(err("workspace 0"), "invalid workspace number '0'");
+        assert_eq!(err("workspace"), "invalid workspace number ''");
+        assert_eq!(
+            err("move-to-workspace 2 stay"),
+            "usage: move-to-workspace <n> [follow]"
+        );
+        assert_eq!(err("set-gaps 4 x"), "invalid gaps '4 x'");
+        assert_eq!(err("set-gaps 4"), "usage: set-gaps <inner> <outer>");
+        assert_eq!(err("set-gaps 4 5000"), "gaps must be at most 1000 pixels");
+        assert_eq!(err("flip diagonal"), "usage: flip horizontal|vertical");
+    }
+}
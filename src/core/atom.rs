@@ -0,0 +1,184 @@
+use x11::xlib;
+
+use crate::core::{node, x};
+
+// Atoms rwm looks at, interned once at startup
+#[derive(Clone, Copy)]
+pub struct Atoms {
+    pub atom: x::Atom,
+    pub cardinal: x::Atom,
+    pub window: x::Atom,
+    pub utf8_string: x::Atom,
+
+    pub wm_protocols: x::Atom,
+    pub wm_state: x::Atom,
+    pub wm_change_state: x::Atom,
+    pub wm_delete_window: x::Atom,
+    pub wm_take_focus: x::Atom,
+
+    pub net_supported: x::Atom,
+    pub net_supporting_wm_check: x::Atom,
+    pub net_wm_name: x::Atom,
+    pub net_client_list: x::Atom,
+    pub net_client_list_stacking: x::Atom,
+    pub net_active_window: x::Atom,
+    pub net_number_of_desktops: x::Atom,
+    pub net_current_desktop: x::Atom,
+    pub net_desktop_names: x::Atom,
+    pub net_workarea: x::Atom,
+    pub net_close_window: x::Atom,
+    pub net_wm_desktop: x::Atom,
+    pub net_moveresize_window: x::Atom,
+    pub net_wm_moveresize: x::Atom,
+    pub net_wm_ping: x::Atom,
+    pub net_wm_pid: x::Atom,
+
+    pub net_wm_state: x::Atom,
+    pub net_wm_state_fullscreen: x::Atom,
+    pub net_wm_state_above: x::Atom,
+    pub net_wm_state_below: x::Atom,
+    pub net_wm_state_sticky: x::Atom,
+    pub net_wm_state_hidden: x::Atom,
+    pub net_wm_state_demands_attention: x::Atom,
+
+    pub net_wm_window_type: x::Atom,
+    pub net_wm_window_type_normal: x::Atom,
+    pub net_wm_window_type_dialog: x::Atom,
+    pub net_wm_window_type_splash: x::Atom,
+    pub net_wm_window_type_dock: x::Atom,
+    pub net_wm_window_type_desktop: x::Atom,
+    pub net_wm_window_type_toolbar: x::Atom,
+    pub net_wm_window_type_utility: x::Atom,
+    pub net_wm_window_type_notification: x::Atom,
+
+    pub net_wm_strut: x::Atom,
+    pub net_wm_strut_partial: x::Atom,
+}
+
+impl Atoms {
+    pub fn new(display: &x::Display) -> Self {
+        Atoms {
+            atom: xlib::XA_ATOM,
+            cardinal: xlib::XA_CARDINAL,
+            window: xlib::XA_WINDOW,
+            utf8_string: display.intern_atom("UTF8_STRING"),
+
+            wm_protocols: display.intern_atom("WM_PROTOCOLS"),
+            wm_state: display.intern_atom("WM_STATE"),
+            wm_change_state: display.intern_atom("WM_CHANGE_STATE"),
+            wm_delete_window: display.intern_atom("WM_DELETE_WINDOW"),
+            wm_take_focus: display.intern_atom("WM_TAKE_FOCUS"),
+
+            net_supported: display.intern_atom("_NET_SUPPORTED"),
+            net_supporting_wm_check: display.intern_atom("_NET_SUPPORTING_WM_CHECK"),
+            net_wm_name: display.intern_atom("_NET_WM_NAME"),
+            net_client_list: display.intern_atom("_NET_CLIENT_LIST"),
+            net_client_list_stacking: display.intern_atom("_NET_CLIENT_LIST_STACKING"),
+            net_active_window: display.intern_atom("_NET_ACTIVE_WINDOW"),
+            net_number_of_desktops: display.intern_atom("_NET_NUMBER_OF_DESKTOPS"),
+            net_current_desktop: display.intern_atom("_NET_CURRENT_DESKTOP"),
+            net_desktop_names: display.intern_atom("_NET_DESKTOP_NAMES"),
+            net_workarea: display.intern_atom("_NET_WORKAREA"),
+            net_close_window: display.intern_atom("_NET_CLOSE_WINDOW"),
+            net_wm_desktop: display.intern_atom("_NET_WM_DESKTOP"),
+            net_moveresize_window: display.intern_atom("_NET_MOVERESIZE_WINDOW"),
+            net_wm_moveresize: display.intern_atom("_NET_WM_MOVERESIZE"),
+            net_wm_ping: display.intern_atom("_NET_WM_PING"),
+            net_wm_pid: display.intern_atom("_NET_WM_PID"),
+
+            net_wm_state: display.intern_atom("_NET_WM_STATE"),
+            net_wm_state_fullscreen: display.intern_atom("_NET_WM_STATE_FULLSCREEN"),
+            net_wm_state_above: display.intern_atom("_NET_WM_STATE_ABOVE"),
+            net_wm_state_below: display.intern_atom("_NET_WM_STATE_BELOW"),
+            net_wm_state_sticky: display.intern_atom("_NET_WM_STATE_STICKY"),
+            net_wm_state_hidden: display.intern_atom("_NET_WM_STATE_HIDDEN"),
+            net_wm_state_demands_attention: display.intern_atom("_NET_WM_STATE_DEMANDS_ATTENTION"),
+
+            net_wm_window_type: display.intern_atom("_NET_WM_WINDOW_TYPE"),
+            net_wm_window_type_normal: display.intern_atom("_NET_WM_WINDOW_TYPE_NORMAL"),
+            net_wm_window_type_dialog: display.intern_atom("_NET_WM_WINDOW_TYPE_DIALOG"),
+            net_wm_window_type_splash: display.intern_atom("_NET_WM_WINDOW_TYPE_SPLASH"),
+            net_wm_window_type_dock: display.intern_atom("_NET_WM_WINDOW_TYPE_DOCK"),
+            net_wm_window_type_desktop: display.intern_atom("_NET_WM_WINDOW_TYPE_DESKTOP"),
+            net_wm_window_type_toolbar: display.intern_atom("_NET_WM_WINDOW_TYPE_TOOLBAR"),
+            net_wm_window_type_utility: display.intern_atom("_NET_WM_WINDOW_TYPE_UTILITY"),
+            net_wm_window_type_notification: display
+                .intern_atom("_NET_WM_WINDOW_TYPE_NOTIFICATION"),
+
+            net_wm_strut: display.intern_atom("_NET_WM_STRUT"),
+            net_wm_strut_partial: display.intern_atom("_NET_WM_STRUT_PARTIAL"),
+        }
+    }
+
+    // Advertised in _NET_SUPPORTED
+    pub fn supported(&self) -> Vec<x::Atom> {
+        vec![
+            self.net_supported,
+            self.net_supporting_wm_check,
+            self.net_wm_name,
+            self.net_client_list,
+            self.net_client_list_stacking,
+            self.net_active_window,
+            self.net_number_of_desktops,
+            self.net_current_desktop,
+            self.net_desktop_names,
+            self.net_workarea,
+            self.net_close_window,
+            self.net_wm_desktop,
+            self.net_moveresize_window,
+            self.net_wm_moveresize,
+            self.net_wm_ping,
+            self.net_wm_pid,
+            self.net_wm_state,
+            self.net_wm_state_fullscreen,
+            self.net_wm_state_above,
+            self.net_wm_state_below,
+            self.net_wm_state_sticky,
+            self.net_wm_state_hidden,
+            self.net_wm_state_demands_attention,
+            self.net_wm_window_type,
+            self.net_wm_window_type_normal,
+            self.net_wm_window_type_dialog,
+            self.net_wm_window_type_splash,
+            self.net_wm_window_type_dock,
+            self.net_wm_window_type_desktop,
+            self.net_wm_window_type_toolbar,
+            self.net_wm_window_type_utility,
+            self.net_wm_window_type_notification,
+            self.net_wm_strut,
+            self.net_wm_strut_partial,
+        ]
+    }
+
+    // Flag of `state` the _NET_WM_STATE_* atom stands for
+    pub fn state_flag<'a>(
+        &self,
+        state: &'a mut node::State,
+        atom: x::Atom,
+    ) -> Option<&'a mut bool> {
+        match atom {
+            a if a == self.net_wm_state_fullscreen => Some(&mut state.fullscreen),
+            a if a == self.net_wm_state_above => Some(&mut state.above),
+            a if a == self.net_wm_state_below => Some(&mut state.below),
+            a if a == self.net_wm_state_sticky => Some(&mut state.sticky),
+            a if a == self.net_wm_state_hidden => Some(&mut state.hidden),
+            a if a == self.net_wm_state_demands_attention => Some(&mut state.urgent),
+            _ => None,
+        }
+    }
+
+    pub fn state_atoms(&self, state: &node::State) -> Vec<x::Atom> {
+        [
+            (state.fullscreen, self.net_wm_state_fullscreen),
+            (state.above, self.net_wm_state_above),
+            (state.below, self.net_wm_state_below),
+            (state.sticky, self.net_wm_state_sticky),
+            (state.hidden, self.net_wm_state_hidden),
+            (state.urgent, self.net_wm_state_demands_attention),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, atom)| *atom)
+        .collect()
+    }
+}
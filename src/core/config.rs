@@ -1,8 +1,754 @@
-// pub const MOD: u32 = keysym::XK_Super_L;
+use serde::{de, Deserialize, Deserializer};
+use std::{collections::HashMap, env, fs, io, path::PathBuf, time::Duration};
+use toml::Spanned;
+use x11::{keysym, xlib};
+
+use crate::core::{
+    action::Action,
+    container::{Direction, Mode, Orientation},
+    key::{KeyBinding, KeySym},
+    layout,
+    mouse::{ButtonBinding, DragKind},
+    x,
+};
+
+// Built-in defaults, used when there is no config file or it is broken
+pub const MOD: u32 = xlib::Mod4Mask;
+pub const TERMINAL: &str = "xterm";
 
 pub const BORDER_WIDTH: u32 = 3;
 pub const BORDER_COLOR: u64 = 0x0022_2222;
 pub const BACKGROUND: u64 = 0x0077_5555;
 
 pub const FOCUSED_BORDER_COLOR: u64 = 0x00ff_0000;
-pub const MARKED_BORDER_COLOR: u64 = 0x00ff_ff00;
+pub const URGENT_BORDER_COLOR: u64 = 0x00ff_8800;
+pub const TAB_TEXT_COLOR: u64 = 0x00ff_ffff;
+
+pub const MASTER_FACTOR: f32 = 0.55;
+pub const MASTER_FACTOR_STEP: f32 = 0.05;
+pub const MASTER_COUNT: usize = 1;
+pub const SPLIT_RATIO_STEP: f32 = 0.05;
+
+pub const GAPS: layout::Gaps = layout::Gaps {
+    inner: 0,
+    outer: 0,
+    smart: false,
+};
+pub const GAP_STEP: i32 = 2;
+
+// Tiled windows get a size their WM_NORMAL_HINTS allow, centered in their tile
+pub const SIZE_HINTS_TILED: bool = true;
+
+// How long a closed window has to answer a ping before it is reported as not responding
+pub const PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub const WORKSPACES: &[&str] = &["1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+// Frame appearance, copied into every window
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub border_width: u32,
+    pub border_color: u64,
+    pub focused_border_color: u64,
+    pub urgent_border_color: u64,
+    pub background: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub class: Option<String>,
+    pub instance: Option<String>,
+    pub title: Option<String>,
+    pub floating: bool,
+    pub workspace: Option<String>,
+}
+
+impl Rule {
+    pub fn matches(&self, class: &str, instance: &str, title: &str) -> bool {
+        self.class.as_ref().is_none_or(|c| c == class)
+            && self.instance.as_ref().is_none_or(|i| i == instance)
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|t| title.contains(t.as_str()))
+    }
+}
+
+pub struct Config {
+    pub mod_key: u32,
+    pub style: Style,
+    pub keybindings: Vec<KeyBinding>,
+    pub mousebindings: Vec<ButtonBinding>,
+    pub layouts: Vec<String>,
+    pub layout_params: layout::Params,
+    pub workspaces: Vec<String>,
+    // Gaps of workspaces that don't use the default ones
+    pub workspace_gaps: HashMap<String, layout::Gaps>,
+    pub rules: Vec<Rule>,
+    pub ping_timeout: Duration,
+    pub size_hints_tiled: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mod_key: MOD,
+            style: Style {
+                border_width: BORDER_WIDTH,
+                border_color: BORDER_COLOR,
+                focused_border_color: FOCUSED_BORDER_COLOR,
+                urgent_border_color: URGENT_BORDER_COLOR,
+                background: BACKGROUND,
+            },
+            keybindings: keybindings(MOD),
+            mousebindings: mousebindings(MOD),
+            layouts: layout::NAMES.iter().map(|name| name.to_string()).collect(),
+            layout_params: layout::Params::new(MASTER_FACTOR, MASTER_COUNT, GAPS),
+            workspaces: WORKSPACES.iter().map(|name| name.to_string()).collect(),
+            workspace_gaps: HashMap::new(),
+            rules: Vec::new(),
+            ping_timeout: PING_TIMEOUT,
+            size_hints_tiled: SIZE_HINTS_TILED,
+        }
+    }
+}
+
+impl Config {
+    // $XDG_CONFIG_HOME/rwm/config.toml, or ~/.config/rwm/config.toml
+    pub fn path() -> Option<PathBuf> {
+        let dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(dir.join("rwm").join("config.toml"))
+    }
+
+    // Never fails: a missing file means defaults, a broken one is reported and ignored
+    pub fn load() -> Config {
+        let path = match Config::path() {
+            Some(path) => path,
+            None => return Config::default(),
+        };
+
+        match Config::from_file(&path) {
+            Ok(Some(config)) => config,
+            Ok(None) => Config::default(),
+            Err(err) => {
+                eprintln!("{}", err);
+                eprintln!("Using default configuration");
+                Config::default()
+            }
+        }
+    }
+
+    pub fn from_file(path: &PathBuf) -> Result<Option<Config>, String> {
+        let src = match fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        };
+
+        Config::parse(&src)
+            .map(Some)
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn gaps(&self, workspace: &str) -> layout::Gaps {
+        self.workspace_gaps
+            .get(workspace)
+            .copied()
+            .unwrap_or(self.layout_params.gaps)
+    }
+
+    pub fn parse(src: &str) -> Result<Config, String> {
+        // toml errors already point at the line and column
+        let file: ConfigFile = toml::from_str(src).map_err(|err| err.to_string())?;
+
+        let mut config = Config::default();
+
+        if let Some(mod_key) = file.mod_key {
+            config.mod_key = mod_key.0;
+            config.keybindings = keybindings(config.mod_key);
+            config.mousebindings = mousebindings(config.mod_key);
+        }
+
+        if let Some(border_width) = file.border_width {
+            config.style.border_width = border_width;
+        }
+
+        if let Some(colors) = file.colors {
+            let style = &mut config.style;
+            style.border_color = colors.border.map_or(style.border_color, |c| c.0);
+            style.focused_border_color = colors.focused.map_or(style.focused_border_color, |c| c.0);
+            style.urgent_border_color = colors.urgent.map_or(style.urgent_border_color, |c| c.0);
+            style.background = colors.background.map_or(style.background, |c| c.0);
+        }
+
+        if let Some(bindings) = file.keybindings {
+            config.keybindings = bindings
+                .into_iter()
+                .map(|binding| KeyBinding {
+                    modifiers: binding.keys.modifiers(config.mod_key),
+                    keysym: binding.keys.keysym,
+                    action: binding.action,
+                })
+                .collect();
+        }
+
+        if let Some(layouts) = file.layouts {
+            if layouts.get_ref().is_empty() {
+                return Err(invalid(
+                    src,
+                    &layouts,
+                    "layouts",
+                    "expected at least one layout",
+                ));
+            }
+            config.layouts = layouts
+                .into_inner()
+                .into_iter()
+                .map(|name| name.0)
+                .collect();
+        }
+
+        if let Some(factor) = file.master_factor {
+            if !(layout::MIN_MASTER_FACTOR..=layout::MAX_MASTER_FACTOR).contains(factor.get_ref()) {
+                let message = format!(
+                    "expected a value between {} and {}",
+                    layout::MIN_MASTER_FACTOR,
+                    layout::MAX_MASTER_FACTOR
+                );
+                return Err(invalid(src, &factor, "master_factor", &message));
+            }
+            config.layout_params.master_factor = factor.into_inner();
+        }
+
+        if let Some(count) = file.master_count {
+            config.layout_params.master_count = count;
+        }
+
+        if let Some(workspaces) = file.workspaces {
+            if workspaces.get_ref().is_empty() {
+                let message = "expected at least one workspace";
+                return Err(invalid(src, &workspaces, "workspaces", message));
+            }
+            config.workspaces = workspaces.into_inner();
+        }
+
+        if let Some(gaps) = file.gaps {
+            let defaults = &mut config.layout_params.gaps;
+            defaults.inner = gaps.inner.unwrap_or(defaults.inner);
+            defaults.outer = gaps.outer.unwrap_or(defaults.outer);
+            defaults.smart = gaps.smart.unwrap_or(defaults.smart);
+            let defaults = *defaults;
+
+            for (name, gaps) in gaps.workspaces.unwrap_or_default() {
+                if !config.workspaces.contains(name.get_ref()) {
+                    let message = format!("unknown workspace '{}'", name.get_ref());
+                    return Err(invalid(src, &name, "gaps.workspaces", &message));
+                }
+                let name = name.into_inner();
+                let gaps = layout::Gaps {
+                    inner: gaps.inner.unwrap_or(defaults.inner),
+                    outer: gaps.outer.unwrap_or(defaults.outer),
+                    smart: gaps.smart.unwrap_or(defaults.smart),
+                };
+                config.workspace_gaps.insert(name, gaps);
+            }
+        }
+
+        if let Some(size_hints) = file.size_hints_tiled {
+            config.size_hints_tiled = size_hints;
+        }
+
+        if let Some(timeout) = file.ping_timeout {
+            config.ping_timeout = Duration::from_millis(timeout);
+        }
+
+        if let Some(rules) = file.rules {
+            let mut parsed = Vec::new();
+            for rule in rules {
+                if let Some(workspace) = rule.workspace.as_ref() {
+                    if !config.workspaces.contains(workspace.get_ref()) {
+                        let message = format!("unknown workspace '{}'", workspace.get_ref());
+                        return Err(invalid(src, workspace, "rules.workspace", &message));
+                    }
+                }
+                parsed.push(Rule {
+                    class: rule.class,
+                    instance: rule.instance,
+                    title: rule.title,
+                    floating: rule.floating,
+                    workspace: rule.workspace.map(Spanned::into_inner),
+                });
+            }
+            config.rules = parsed;
+        }
+
+        Ok(config)
+    }
+}
+
+// Errors found after parsing name the key and the line of its value. Only the line:
+// toml 0.5 spans are not exact for every type, floats start after the decimal point.
+fn invalid<T>(src: &str, value: &Spanned<T>, key: &str, message: &str) -> String {
+    let line = src[..value.start()].matches('\n').count() + 1;
+    format!("{} for key `{}` on line {}", message, key, line)
+}
+
+pub fn keybindings(mod_key: u32) -> Vec<KeyBinding> {
+    let mut bindings = vec![
+        KeyBinding::new(mod_key, keysym::XK_j, Action::FocusNext),
+        KeyBinding::new(mod_key, keysym::XK_k, Action::FocusPrev),
+        KeyBinding::new(mod_key | xlib::ShiftMask, keysym::XK_c, Action::Close),
+        KeyBinding::new(
+            mod_key | xlib::ShiftMask | xlib::ControlMask,
+            keysym::XK_c,
+            Action::Kill,
+        ),
+        KeyBinding::new(
+            mod_key,
+            keysym::XK_Return,
+            Action::Spawn(TERMINAL.to_string()),
+        ),
+        KeyBinding::new(mod_key | xlib::ShiftMask, keysym::XK_r, Action::Reload),
+        KeyBinding::new(mod_key, keysym::XK_space, Action::NextLayout),
+        KeyBinding::new(
+            mod_key | xlib::ShiftMask,
+            keysym::XK_space,
+            Action::PrevLayout,
+        ),
+        KeyBinding::new(mod_key, keysym::XK_l, Action::GrowMaster),
+        KeyBinding::new(mod_key, keysym::XK_h, Action::ShrinkMaster),
+        KeyBinding::new(mod_key, keysym::XK_i, Action::IncMaster),
+        KeyBinding::new(mod_key, keysym::XK_d, Action::DecMaster),
+        KeyBinding::new(mod_key | xlib::ShiftMask, keysym::XK_Return, Action::Zoom),
+        KeyBinding::new(
+            mod_key | xlib::ShiftMask,
+            keysym::XK_f,
+            Action::ToggleFloating,
+        ),
+        KeyBinding::new(
+            mod_key | xlib::ControlMask,
+            keysym::XK_h,
+            Action::Preselect(Some(Direction::Left)),
+        ),
+        KeyBinding::new(
+            mod_key | xlib::ControlMask,
+            keysym::XK_j,
+            Action::Preselect(Some(Direction::Down)),
+        ),
+        KeyBinding::new(
+            mod_key | xlib::ControlMask,
+            keysym::XK_k,
+            Action::Preselect(Some(Direction::Up)),
+        ),
+        KeyBinding::new(
+            mod_key | xlib::ControlMask,
+            keysym::XK_l,
+            Action::Preselect(Some(Direction::Right)),
+        ),
+        KeyBinding::new(
+            mod_key | xlib::ControlMask,
+            keysym::XK_space,
+            Action::Preselect(None),
+        ),
+        KeyBinding::new(mod_key | xlib::ShiftMask, keysym::XK_l, Action::GrowSplit),
+        KeyBinding::new(mod_key | xlib::ShiftMask, keysym::XK_h, Action::ShrinkSplit),
+        KeyBinding::new(mod_key, keysym::XK_r, Action::Rotate),
+        KeyBinding::new(mod_key, keysym::XK_x, Action::Flip(Orientation::Horizontal)),
+        KeyBinding::new(mod_key, keysym::XK_y, Action::Flip(Orientation::Vertical)),
+        KeyBinding::new(mod_key, keysym::XK_e, Action::ContainerMode(Mode::Split)),
+        KeyBinding::new(mod_key, keysym::XK_w, Action::ContainerMode(Mode::Tabbed)),
+        KeyBinding::new(mod_key, keysym::XK_s, Action::ContainerMode(Mode::Stacked)),
+        KeyBinding::new(mod_key, keysym::XK_equal, Action::IncGaps),
+        KeyBinding::new(mod_key, keysym::XK_minus, Action::DecGaps),
+        KeyBinding::new(
+            mod_key | xlib::ShiftMask,
+            keysym::XK_equal,
+            Action::IncOuterGaps,
+        ),
+        KeyBinding::new(
+            mod_key | xlib::ShiftMask,
+            keysym::XK_minus,
+            Action::DecOuterGaps,
+        ),
+        KeyBinding::new(mod_key, keysym::XK_g, Action::ToggleSmartGaps),
+        KeyBinding::new(mod_key, keysym::XK_period, Action::FocusNextMonitor),
+        KeyBinding::new(mod_key, keysym::XK_comma, Action::FocusPrevMonitor),
+        KeyBinding::new(
+            mod_key | xlib::ShiftMask,
+            keysym::XK_period,
+            Action::MoveToNextMonitor,
+        ),
+        KeyBinding::new(
+            mod_key | xlib::ShiftMask,
+            keysym::XK_comma,
+            Action::MoveToPrevMonitor,
+        ),
+    ];
+
+    // Mod+N shows workspace N, Mod+Shift+N sends the focused window there
+    for i in 0..WORKSPACES.len() {
+        let keysym = keysym::XK_1 + i as u32;
+        bindings.push(KeyBinding::new(mod_key, keysym, Action::Workspace(i)));
+        bindings.push(KeyBinding::new(
+            mod_key | xlib::ShiftMask,
+            keysym,
+            Action::MoveToWorkspace(i, false),
+        ));
+    }
+
+    bindings
+}
+
+pub fn mousebindings(mod_key: u32) -> Vec<ButtonBinding> {
+    vec![
+        ButtonBinding::new(mod_key, xlib::Button1, DragKind::Move),
+        ButtonBinding::new(mod_key, xlib::Button3, DragKind::Resize),
+    ]
+}
+
+// On-disk representation, every field is optional and falls back to the defaults
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    mod_key: Option<ModKey>,
+    border_width: Option<u32>,
+    colors: Option<ColorsFile>,
+    keybindings: Option<Vec<KeyBindingFile>>,
+    layouts: Option<Spanned<Vec<LayoutName>>>,
+    master_factor: Option<Spanned<f32>>,
+    master_count: Option<usize>,
+    workspaces: Option<Spanned<Vec<String>>>,
+    gaps: Option<GapsFile>,
+    rules: Option<Vec<RuleFile>>,
+    size_hints_tiled: Option<bool>,
+    // Milliseconds
+    ping_timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorsFile {
+    border: Option<Color>,
+    focused: Option<Color>,
+    urgent: Option<Color>,
+    background: Option<Color>,
+}
+
+// Defaults for every workspace, and overrides in `[gaps.workspaces.<name>]`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GapsFile {
+    inner: Option<u32>,
+    outer: Option<u32>,
+    smart: Option<bool>,
+    workspaces: Option<HashMap<Spanned<String>, WorkspaceGapsFile>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspaceGapsFile {
+    inner: Option<u32>,
+    outer: Option<u32>,
+    smart: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyBindingFile {
+    keys: Keys,
+    #[serde(deserialize_with = "deserialize_action")]
+    action: Action,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    class: Option<String>,
+    instance: Option<String>,
+    title: Option<String>,
+    #[serde(default)]
+    floating: bool,
+    workspace: Option<Spanned<String>>,
+}
+
+// `#rrggbb`
+struct Color(u64);
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match s.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => u64::from_str_radix(hex, 16)
+                .map(Color)
+                .map_err(|_| de::Error::custom(format!("invalid color '{}'", s))),
+            _ => Err(de::Error::custom(format!(
+                "invalid color '{}', expected #rrggbb",
+                s
+            ))),
+        }
+    }
+}
+
+struct ModKey(u32);
+
+impl<'de> Deserialize<'de> for ModKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match modifier_mask(&s) {
+            Some(mask) => Ok(ModKey(mask)),
+            None => Err(de::Error::custom(format!("unknown modifier '{}'", s))),
+        }
+    }
+}
+
+// `Mod+Shift+Return`, where `Mod` stands for the configured mod key
+struct Keys {
+    modifiers: u32,
+    uses_mod: bool,
+    keysym: KeySym,
+}
+
+impl Keys {
+    fn modifiers(&self, mod_key: u32) -> u32 {
+        if self.uses_mod {
+            self.modifiers | mod_key
+        } else {
+            self.modifiers
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+
+        let mut keys = Keys {
+            modifiers: 0,
+            uses_mod: false,
+            keysym: 0,
+        };
+
+        for part in parts {
+            if part.eq_ignore_ascii_case("mod") {
+                keys.uses_mod = true;
+            } else {
+                keys.modifiers |= modifier_mask(part)
+                    .ok_or_else(|| de::Error::custom(format!("unknown modifier '{}'", part)))?;
+            }
+        }
+
+        keys.keysym = x::string_to_keysym(key)
+            .ok_or_else(|| de::Error::custom(format!("unknown key '{}'", key)))?;
+
+        Ok(keys)
+    }
+}
+
+struct LayoutName(String);
+
+impl<'de> Deserialize<'de> for LayoutName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if layout::NAMES.contains(&s.as_str()) {
+            Ok(LayoutName(s))
+        } else {
+            Err(de::Error::custom(format!(
+                "unknown layout '{}', expected one of: {}",
+                s,
+                layout::NAMES.join(", ")
+            )))
+        }
+    }
+}
+
+fn deserialize_action<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Action, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
+
+fn modifier_mask(name: &str) -> Option<u32> {
+    match name.to_lowercase().as_str() {
+        "shift" => Some(xlib::ShiftMask),
+        "ctrl" | "control" => Some(xlib::ControlMask),
+        "alt" | "mod1" => Some(xlib::Mod1Mask),
+        "mod2" => Some(xlib::Mod2Mask),
+        "mod3" => Some(xlib::Mod3Mask),
+        "super" | "mod4" => Some(xlib::Mod4Mask),
+        "mod5" => Some(xlib::Mod5Mask),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &str) -> String {
+        match Config::parse(src) {
+            Ok(_) => panic!("parsed invalid config:\n{}", src),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn empty_file_gives_defaults() {
+        let config = Config::parse("").unwrap();
+        let defaults = Config::default();
+        assert_eq!(config.mod_key, MOD);
+        assert_eq!(config.style.border_width, BORDER_WIDTH);
+        assert_eq!(config.layouts, defaults.layouts);
+        assert_eq!(config.workspaces, defaults.workspaces);
+        assert_eq!(config.keybindings.len(), defaults.keybindings.len());
+        assert_eq!(config.ping_timeout, PING_TIMEOUT);
+        assert_eq!(config.size_hints_tiled, SIZE_HINTS_TILED);
+        assert!(config.rules.is_empty());
+    }
+
+    #[test]
+    fn parses_every_field() {
+        let config = Config::parse(
+            r##"
+            mod_key = "alt"
+            border_width = 5
+            layouts = ["row", "master"]
+            master_factor = 0.6
+            master_count = 2
+            workspaces = ["web", "code"]
+            size_hints_tiled = false
+            ping_timeout = 500
+
+            [colors]
+            border = "#102030"
+            focused = "#ABCDEF"
+            background = "#000000"
+
+            [gaps]
+            inner = 4
+            outer = 8
+            smart = true
+
+            [[keybindings]]
+            keys = "Mod+Shift+Return"
+            action = "spawn xterm -e top"
+
+            [[rules]]
+            class = "Firefox"
+            floating = true
+            workspace = "web"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.mod_key, xlib::Mod1Mask);
+        assert_eq!(config.mousebindings[0].modifiers, xlib::Mod1Mask);
+        assert_eq!(config.style.border_width, 5);
+        assert_eq!(config.style.border_color, 0x10_2030);
+        assert_eq!(config.style.focused_border_color, 0xab_cdef);
+        assert_eq!(config.style.background, 0);
+        assert_eq!(config.layouts, vec!["row", "master"]);
+        assert_eq!(config.layout_params.master_factor, 0.6);
+        assert_eq!(config.layout_params.master_count, 2);
+        assert_eq!(config.workspaces, vec!["web", "code"]);
+        assert!(!config.size_hints_tiled);
+        assert_eq!(config.ping_timeout, Duration::from_millis(500));
+
+        let gaps = config.layout_params.gaps;
+        assert_eq!((gaps.inner, gaps.outer, gaps.smart), (4, 8, true));
+
+        assert_eq!(config.keybindings.len(), 1);
+        let binding = &config.keybindings[0];
+        assert_eq!(binding.modifiers, xlib::Mod1Mask | xlib::ShiftMask);
+        assert_eq!(binding.keysym, keysym::XK_Return as KeySym);
+        assert!(matches!(&binding.action, Action::Spawn(command) if command == "xterm -e top"));
+
+        assert_eq!(config.rules.len(), 1);
+        assert!(config.rules[0].matches("Firefox", "Navigator", "Mozilla Firefox"));
+        assert!(config.rules[0].floating);
+        assert_eq!(config.rules[0].workspace.as_deref(), Some("web"));
+    }
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let config = Config::parse(
+            r##"
+            [colors]
+            focused = "#00ff00"
+
+            [gaps]
+            inner = 6
+
+            [gaps.workspaces.2]
+            outer = 10
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.style.focused_border_color, 0x00ff00);
+        assert_eq!(config.style.border_color, BORDER_COLOR);
+        assert_eq!(config.style.background, BACKGROUND);
+        assert_eq!(config.mod_key, MOD);
+        assert_eq!(config.layout_params.master_factor, MASTER_FACTOR);
+
+        let gaps = config.gaps("1");
+        assert_eq!(
+            (gaps.inner, gaps.outer, gaps.smart),
+            (6, GAPS.outer, GAPS.smart)
+        );
+        // Workspace overrides fall back to the configured defaults, not the built-in ones
+        let gaps = config.gaps("2");
+        assert_eq!((gaps.inner, gaps.outer, gaps.smart), (6, 10, GAPS.smart));
+    }
+
+    #[test]
+    fn invalid_values() {
+        let err = error("[colors]\nborder = \"#12345\"");
+        assert!(err.contains("invalid color '#12345'"), "{}", err);
+        let err = error("[colors]\nborder = \"#gggggg\"");
+        assert!(err.contains("invalid color '#gggggg'"), "{}", err);
+        let err = error("mod_key = \"hyper\"");
+        assert!(err.contains("unknown modifier 'hyper'"), "{}", err);
+        let err = error("[[keybindings]]\nkeys = \"Mod+Meta+a\"\naction = \"close\"");
+        assert!(err.contains("unknown modifier 'Meta'"), "{}", err);
+        let err = error("[[keybindings]]\nkeys = \"Mod+NoSuchKey\"\naction = \"close\"");
+        assert!(err.contains("unknown key 'NoSuchKey'"), "{}", err);
+        let err = error("[[keybindings]]\nkeys = \"Mod+a\"\naction = \"explode\"");
+        assert!(err.contains("unknown action 'explode'"), "{}", err);
+        let err = error("layouts = [\"row\", \"cascade\"]");
+        assert!(err.contains("unknown layout 'cascade'"), "{}", err);
+        let err = error("layouts = []");
+        assert_eq!(
+            err,
+            "expected at least one layout for key `layouts` on line 1"
+        );
+        let err = error("border_width = 2\nmaster_factor = 1.5");
+        assert_eq!(
+            err,
+            "expected a value between 0.05 and 0.95 for key `master_factor` on line 2"
+        );
+        let err = error("workspaces = []");
+        assert_eq!(
+            err,
+            "expected at least one workspace for key `workspaces` on line 1"
+        );
+        let err =
+            error("workspaces = [\"a\", \"b\"]\n\n[[rules]]\nclass = \"x\"\nworkspace = \"c\"");
+        assert_eq!(
+            err,
+            "unknown workspace 'c' for key `rules.workspace` on line 5"
+        );
+        let err = error("workspaces = [\"a\"]\n[gaps.workspaces.b]\ninner = 1");
+        assert_eq!(
+            err,
+            "unknown workspace 'b' for key `gaps.workspaces` on line 2"
+        );
+        let err = error("border_colour = \"#ffffff\"");
+        assert!(err.contains("unknown field `border_colour`"), "{}", err);
+    }
+}
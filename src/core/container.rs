@@ -1,13 +1,673 @@
-use crate::core::node;
+use crate::core::{node::NodeID, window::Rect};
 
-pub struct Container {
-    windows: Vec<dyn Window>;
+pub const MIN_RATIO: f32 = 0.1;
+pub const MAX_RATIO: f32 = 0.9;
+
+// Height of a single tab
+pub const TAB_HEIGHT: u32 = 18;
+
+// `Horizontal` puts the children side by side, `Vertical` one above the other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+// Tabbed and stacked containers show one of their windows at a time, under a strip with
+// one tab per window: side by side for `Tabbed`, one per line for `Stacked`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Split,
+    Tabbed,
+    Stacked,
+}
+
+// Side of the focused window the next one goes to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    fn orientation(self) -> Orientation {
+        match self {
+            Direction::Left | Direction::Right => Orientation::Horizontal,
+            Direction::Up | Direction::Down => Orientation::Vertical,
+        }
+    }
+
+    fn goes_first(self) -> bool {
+        matches!(self, Direction::Left | Direction::Up)
+    }
+}
+
+// Either a window or a split of the area between two containers
+#[derive(Debug, Clone)]
+pub enum Container {
+    Window(NodeID),
+    Split(Box<Split>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Split {
+    pub orientation: Orientation,
+    // Share of the area taken by the first child
+    pub ratio: f32,
+    pub first: Container,
+    pub second: Container,
+
+    pub mode: Mode,
+    // Last focused window inside, the one shown when tabbed or stacked
+    pub active: Option<NodeID>,
+}
+
+// Tab strip of a tabbed or stacked container
+#[derive(Debug, Clone)]
+pub struct Tabs {
+    pub rect: Rect,
+    pub mode: Mode,
+    pub windows: Vec<NodeID>,
+    pub active: NodeID,
 }
 
 impl Container {
-    fn new() -> Self {
-        Container {
-            windows: Vec::new(),
+    pub fn is(&self, id: NodeID) -> bool {
+        matches!(self, Container::Window(win) if *win == id)
+    }
+
+    pub fn contains(&self, id: NodeID) -> bool {
+        match self {
+            Container::Window(win) => *win == id,
+            Container::Split(split) => split.first.contains(id) || split.second.contains(id),
+        }
+    }
+
+    fn windows(&self, windows: &mut Vec<NodeID>) {
+        match self {
+            Container::Window(win) => windows.push(*win),
+            Container::Split(split) => {
+                split.first.windows(windows);
+                split.second.windows(windows);
+            }
+        }
+    }
+
+    fn arrange(&self, area: Rect, rects: &mut Vec<(NodeID, Rect)>, tabs: &mut Vec<Tabs>) {
+        match self {
+            Container::Window(win) => rects.push((*win, area)),
+            Container::Split(split) if split.mode == Mode::Split => {
+                let (first, second) = split.areas(&area);
+                split.first.arrange(first, rects, tabs);
+                split.second.arrange(second, rects, tabs);
+            }
+            // Every window inside gets a tab, nested containers included
+            Container::Split(split) => {
+                let mut windows = Vec::new();
+                self.windows(&mut windows);
+
+                let lines = match split.mode {
+                    Mode::Stacked => windows.len() as u32,
+                    _ => 1,
+                };
+                let height = (TAB_HEIGHT * lines).min(area.height);
+                let content = Rect::new(
+                    area.x,
+                    area.y + height as i32,
+                    area.width,
+                    area.height - height,
+                );
+
+                for win in windows.iter() {
+                    rects.push((*win, content));
+                }
+                tabs.push(Tabs {
+                    rect: Rect::new(area.x, area.y, area.width, height),
+                    mode: split.mode,
+                    active: split
+                        .active
+                        .filter(|id| windows.contains(id))
+                        .unwrap_or(windows[0]),
+                    windows,
+                });
+            }
+        }
+    }
+
+    // Replaces the `target` window with a split holding it and `win`
+    fn insert(&mut self, target: NodeID, win: NodeID, direction: Direction) -> bool {
+        match self {
+            Container::Window(id) if *id == target => {
+                let (first, second) = if direction.goes_first() {
+                    (Container::Window(win), Container::Window(target))
+                } else {
+                    (Container::Window(target), Container::Window(win))
+                };
+                *self = Container::Split(Box::new(Split {
+                    orientation: direction.orientation(),
+                    ratio: 0.5,
+                    first,
+                    second,
+                    mode: Mode::Split,
+                    active: Some(win),
+                }));
+                true
+            }
+            Container::Window(_) => false,
+            Container::Split(split) => {
+                split.first.insert(target, win, direction)
+                    || split.second.insert(target, win, direction)
+            }
+        }
+    }
+
+    // What is left once `id` is gone, splits with a single child collapse into it
+    fn remove(self, id: NodeID) -> Option<Container> {
+        match self {
+            Container::Window(win) if win == id => None,
+            Container::Window(_) => Some(self),
+            Container::Split(mut split) => {
+                match (split.first.remove(id), split.second.remove(id)) {
+                    (Some(first), Some(second)) => {
+                        split.first = first;
+                        split.second = second;
+                        Some(Container::Split(split))
+                    }
+                    (Some(child), None) | (None, Some(child)) => Some(child),
+                    (None, None) => None,
+                }
+            }
+        }
+    }
+
+    // Split directly holding the `id` window
+    fn parent_mut(&mut self, id: NodeID) -> Option<&mut Split> {
+        let split = match self {
+            Container::Window(_) => return None,
+            Container::Split(split) => split,
+        };
+
+        if split.first.is(id) || split.second.is(id) {
+            Some(split)
+        } else if split.first.contains(id) {
+            split.first.parent_mut(id)
+        } else {
+            split.second.parent_mut(id)
+        }
+    }
+
+    fn activate(&mut self, id: NodeID) {
+        if let Container::Split(split) = self {
+            if split.first.contains(id) || split.second.contains(id) {
+                split.active = Some(id);
+                split.first.activate(id);
+                split.second.activate(id);
+            }
+        }
+    }
+
+    fn rotate(&mut self) {
+        if let Container::Split(split) = self {
+            split.rotate();
+        }
+    }
+
+    fn flip(&mut self, orientation: Orientation) {
+        if let Container::Split(split) = self {
+            split.flip(orientation);
+        }
+    }
+}
+
+impl Split {
+    fn areas(&self, area: &Rect) -> (Rect, Rect) {
+        match self.orientation {
+            Orientation::Horizontal => {
+                let width = (area.width as f32 * self.ratio) as u32;
+                (
+                    Rect::new(area.x, area.y, width, area.height),
+                    Rect::new(
+                        area.x + width as i32,
+                        area.y,
+                        area.width - width,
+                        area.height,
+                    ),
+                )
+            }
+            Orientation::Vertical => {
+                let height = (area.height as f32 * self.ratio) as u32;
+                (
+                    Rect::new(area.x, area.y, area.width, height),
+                    Rect::new(
+                        area.x,
+                        area.y + height as i32,
+                        area.width,
+                        area.height - height,
+                    ),
+                )
+            }
+        }
+    }
+
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.first, &mut self.second);
+        self.ratio = 1.0 - self.ratio;
+    }
+
+    // Quarter turn clockwise: left/right becomes top/bottom, top/bottom becomes right/left
+    fn rotate(&mut self) {
+        self.orientation = match self.orientation {
+            Orientation::Horizontal => Orientation::Vertical,
+            Orientation::Vertical => {
+                self.swap();
+                Orientation::Horizontal
+            }
+        };
+        self.first.rotate();
+        self.second.rotate();
+    }
+
+    // Mirror image, `Horizontal` swaps left and right
+    fn flip(&mut self, orientation: Orientation) {
+        if self.orientation == orientation {
+            self.swap();
+        }
+        self.first.flip(orientation);
+        self.second.flip(orientation);
+    }
+}
+
+// Binary space partitioning of the tiled windows of a workspace
+#[derive(Debug, Clone, Default)]
+pub struct Tree {
+    root: Option<Container>,
+    // Where the next window goes, instead of splitting the longest side
+    preselection: Option<Direction>,
+}
+
+impl Tree {
+    pub fn contains(&self, id: NodeID) -> bool {
+        self.root.as_ref().is_some_and(|root| root.contains(id))
+    }
+
+    pub fn windows(&self) -> Vec<NodeID> {
+        let mut windows = Vec::new();
+        if let Some(root) = self.root.as_ref() {
+            root.windows(&mut windows);
+        }
+        windows
+    }
+
+    pub fn arrange(&self, area: &Rect) -> Vec<(NodeID, Rect)> {
+        self.layout(area).0
+    }
+
+    pub fn tabs(&self, area: &Rect) -> Vec<Tabs> {
+        self.layout(area).1
+    }
+
+    fn layout(&self, area: &Rect) -> (Vec<(NodeID, Rect)>, Vec<Tabs>) {
+        let mut rects = Vec::new();
+        let mut tabs = Vec::new();
+        if let Some(root) = self.root.as_ref() {
+            root.arrange(*area, &mut rects, &mut tabs);
+        }
+        (rects, tabs)
+    }
+
+    pub fn preselect(&mut self, direction: Option<Direction>) {
+        self.preselection = direction;
+    }
+
+    // Splits the `target` window, or the last one when it isn't in the tree
+    pub fn insert(&mut self, win: NodeID, target: Option<NodeID>, area: Option<&Rect>) {
+        if self.contains(win) {
+            return;
+        }
+
+        let target = match target.filter(|id| self.contains(*id)) {
+            Some(target) => target,
+            None => match self.windows().last() {
+                Some(last) => *last,
+                None => {
+                    self.root = Some(Container::Window(win));
+                    return;
+                }
+            },
+        };
+
+        let direction = self.preselection.take().unwrap_or_else(|| {
+            let rect = area.and_then(|area| {
+                self.arrange(area)
+                    .into_iter()
+                    .find(|(id, _)| *id == target)
+                    .map(|(_, rect)| rect)
+            });
+            match rect {
+                Some(rect) if rect.height > rect.width => Direction::Down,
+                _ => Direction::Right,
+            }
+        });
+
+        if let Some(root) = self.root.as_mut() {
+            root.insert(target, win, direction);
+        }
+    }
+
+    pub fn remove(&mut self, id: NodeID) {
+        self.root = self.root.take().and_then(|root| root.remove(id));
+    }
+
+    // Makes the tree hold exactly `windows`, new ones split the `focused` one
+    pub fn sync(&mut self, windows: &[NodeID], focused: Option<NodeID>, area: &Rect) {
+        for id in self.windows() {
+            if !windows.contains(&id) {
+                self.remove(id);
+            }
+        }
+
+        for id in windows {
+            self.insert(*id, focused, Some(area));
+        }
+
+        if let (Some(root), Some(focused)) = (self.root.as_mut(), focused) {
+            root.activate(focused);
+        }
+    }
+
+    // Switches the container holding the window and its sibling, false when the window
+    // is alone in the tree and there is no container to switch
+    pub fn set_mode(&mut self, id: NodeID, mode: Mode) -> bool {
+        match self.root.as_mut().and_then(|root| root.parent_mut(id)) {
+            Some(split) => {
+                split.mode = mode;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Grows the window by moving the split it shares with its sibling
+    pub fn resize(&mut self, id: NodeID, delta: f32) {
+        if let Some(split) = self.root.as_mut().and_then(|root| root.parent_mut(id)) {
+            let delta = if split.first.is(id) { delta } else { -delta };
+            split.ratio = (split.ratio + delta).clamp(MIN_RATIO, MAX_RATIO);
+        }
+    }
+
+    // Rotates the container holding the window and its sibling
+    pub fn rotate(&mut self, id: NodeID) {
+        if let Some(split) = self.root.as_mut().and_then(|root| root.parent_mut(id)) {
+            split.rotate();
+        }
+    }
+
+    pub fn flip(&mut self, id: NodeID, orientation: Orientation) {
+        if let Some(split) = self.root.as_mut().and_then(|root| root.parent_mut(id)) {
+            split.flip(orientation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area() -> Rect {
+        Rect::new(0, 0, 200, 100)
+    }
+
+    fn build(windows: &[NodeID]) -> Tree {
+        let mut tree = Tree::default();
+        for win in windows {
+            tree.insert(*win, tree.windows().last().copied(), Some(&area()));
+        }
+        tree
+    }
+
+    // 1 on the left, 2 above 3 on the right
+    fn nested() -> Tree {
+        let mut tree = build(&[1, 2]);
+        tree.preselect(Some(Direction::Down));
+        tree.insert(3, Some(2), Some(&area()));
+        tree
+    }
+
+    fn root_ratio(tree: &Tree) -> f32 {
+        match tree.root.as_ref() {
+            Some(Container::Split(split)) => split.ratio,
+            _ => panic!("root is not a split"),
         }
     }
+
+    #[test]
+    fn insert_splits_the_longest_side() {
+        let mut tree = build(&[1]);
+        assert_eq!(tree.arrange(&area()), vec![(1, area())]);
+
+        tree.insert(2, Some(1), Some(&area()));
+        tree.insert(3, Some(2), Some(&area()));
+        tree.insert(4, Some(3), Some(&area()));
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 100, 100)),
+                (2, Rect::new(100, 0, 50, 100)),
+                (3, Rect::new(150, 0, 50, 50)),
+                (4, Rect::new(150, 50, 50, 50)),
+            ]
+        );
+
+        // Already there, or split after the last window for unknown targets
+        tree.insert(2, Some(1), Some(&area()));
+        tree.insert(5, Some(99), Some(&area()));
+        assert_eq!(tree.windows(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(
+            tree.arrange(&area())[3..],
+            [
+                (4, Rect::new(150, 50, 25, 50)),
+                (5, Rect::new(175, 50, 25, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn preselection_is_used_once() {
+        let mut tree = build(&[1]);
+        tree.preselect(Some(Direction::Left));
+        tree.insert(2, Some(1), Some(&area()));
+        tree.insert(3, Some(1), Some(&area()));
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (2, Rect::new(0, 0, 100, 100)),
+                (1, Rect::new(100, 0, 50, 100)),
+                (3, Rect::new(150, 0, 50, 100)),
+            ]
+        );
+
+        tree.preselect(Some(Direction::Up));
+        tree.insert(4, Some(2), Some(&area()));
+        assert_eq!(
+            tree.arrange(&area())[..2],
+            [
+                (4, Rect::new(0, 0, 100, 50)),
+                (2, Rect::new(0, 50, 100, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_collapses_splits() {
+        let mut tree = build(&[1, 2, 3]);
+        tree.remove(2);
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 100, 100)),
+                (3, Rect::new(100, 0, 100, 100)),
+            ]
+        );
+
+        tree.remove(99);
+        tree.remove(1);
+        assert_eq!(tree.arrange(&area()), vec![(3, area())]);
+
+        tree.remove(3);
+        assert!(tree.windows().is_empty());
+        assert!(tree.arrange(&area()).is_empty());
+        assert!(!tree.contains(3));
+    }
+
+    #[test]
+    fn collapsing_keeps_the_outer_ratio() {
+        let mut tree = build(&[1, 2, 3]);
+        tree.resize(1, 0.25);
+        tree.remove(3);
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 150, 100)),
+                (2, Rect::new(150, 0, 50, 100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn resize_moves_the_shared_split() {
+        let mut tree = build(&[1, 2]);
+        tree.resize(2, 0.25);
+        assert_eq!(root_ratio(&tree), 0.25);
+
+        tree.resize(2, 1.0);
+        assert_eq!(root_ratio(&tree), MIN_RATIO);
+        tree.resize(1, 5.0);
+        assert_eq!(root_ratio(&tree), MAX_RATIO);
+
+        // A lone window has no split to move
+        let mut tree = build(&[1]);
+        tree.resize(1, 0.25);
+        assert_eq!(tree.arrange(&area()), vec![(1, area())]);
+    }
+
+    #[test]
+    fn rotate_turns_nested_splits_clockwise() {
+        let mut tree = nested();
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 100, 100)),
+                (2, Rect::new(100, 0, 100, 50)),
+                (3, Rect::new(100, 50, 100, 50)),
+            ]
+        );
+
+        tree.rotate(1);
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 200, 50)),
+                (3, Rect::new(0, 50, 100, 50)),
+                (2, Rect::new(100, 50, 100, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotate_only_turns_the_parent_split() {
+        let mut tree = nested();
+        tree.resize(2, 0.25);
+        tree.rotate(2);
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 100, 100)),
+                (3, Rect::new(100, 0, 25, 100)),
+                (2, Rect::new(125, 0, 75, 100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn flip_mirrors_matching_splits() {
+        let mut tree = nested();
+        tree.flip(1, Orientation::Horizontal);
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (2, Rect::new(0, 0, 100, 50)),
+                (3, Rect::new(0, 50, 100, 50)),
+                (1, Rect::new(100, 0, 100, 100)),
+            ]
+        );
+
+        let mut tree = nested();
+        tree.flip(1, Orientation::Vertical);
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 100, 100)),
+                (3, Rect::new(100, 0, 100, 50)),
+                (2, Rect::new(100, 50, 100, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_adds_and_removes_windows() {
+        let mut tree = Tree::default();
+        tree.sync(&[1, 2, 3], Some(1), &area());
+        assert_eq!(tree.windows(), vec![1, 3, 2]);
+        assert_eq!(
+            tree.arrange(&area()),
+            vec![
+                (1, Rect::new(0, 0, 50, 100)),
+                (3, Rect::new(50, 0, 50, 100)),
+                (2, Rect::new(100, 0, 100, 100)),
+            ]
+        );
+
+        tree.sync(&[3, 2], Some(3), &area());
+        assert_eq!(tree.windows(), vec![3, 2]);
+        assert_eq!(tree.arrange(&area())[0], (3, Rect::new(0, 0, 100, 100)));
+    }
+
+    #[test]
+    fn tabbed_and_stacked_containers() {
+        let mut tree = nested();
+        tree.sync(&[1, 2, 3], Some(2), &area());
+        tree.set_mode(3, Mode::Tabbed);
+
+        let content = Rect::new(100, 18, 100, 82);
+        assert_eq!(tree.arrange(&area())[1..], [(2, content), (3, content)]);
+        let tabs = tree.tabs(&area());
+        assert_eq!(tabs.len(), 1);
+        assert_eq!(tabs[0].rect, Rect::new(100, 0, 100, TAB_HEIGHT));
+        assert_eq!(tabs[0].windows, vec![2, 3]);
+        assert_eq!(tabs[0].active, 2);
+
+        tree.set_mode(2, Mode::Stacked);
+        let tabs = tree.tabs(&area());
+        assert_eq!(tabs[0].rect, Rect::new(100, 0, 100, 2 * TAB_HEIGHT));
+        assert_eq!(tree.arrange(&area())[2], (3, Rect::new(100, 36, 100, 64)));
+
+        // Nested containers are tabs of the outer one
+        tree.set_mode(1, Mode::Tabbed);
+        let tabs = tree.tabs(&area());
+        assert_eq!(tabs.len(), 1);
+        assert_eq!(tabs[0].windows, vec![1, 2, 3]);
+        assert_eq!(tabs[0].active, 2);
+    }
+
+    #[test]
+    fn single_window_has_no_container() {
+        let mut tree = build(&[1]);
+        assert!(!tree.set_mode(1, Mode::Tabbed));
+        assert!(tree.tabs(&area()).is_empty());
+        assert_eq!(tree.arrange(&area()), [(1, area())]);
+
+        assert!(!Tree::default().set_mode(1, Mode::Tabbed));
+    }
 }
@@ -1 +1,5 @@
 pub type CursorID = u64;
+
+// Shapes from X11/cursorfont.h
+pub const MOVE: u32 = 52; // XC_fleur
+pub const RESIZE: u32 = 120; // XC_sizing
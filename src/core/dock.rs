@@ -0,0 +1,127 @@
+use crate::core::{
+    layout,
+    window::{Rect, WindowID},
+};
+
+// Space a panel reserves along the edges of the screen, from _NET_WM_STRUT_PARTIAL
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+
+    // Part of each edge that is reserved, ends included
+    pub left_range: (i32, i32),
+    pub right_range: (i32, i32),
+    pub top_range: (i32, i32),
+    pub bottom_range: (i32, i32),
+}
+
+impl Strut {
+    // Twelve values of _NET_WM_STRUT_PARTIAL, or the four of _NET_WM_STRUT which reserve
+    // whole edges
+    pub fn from_values(values: &[u64]) -> Option<Self> {
+        let value = |i: usize| values[i].min(u32::MAX as u64) as u32;
+        let range = |i: usize| (values[i] as i32, values[i + 1] as i32);
+        let whole = (i32::MIN, i32::MAX);
+
+        match values.len() {
+            n if n >= 12 => Some(Strut {
+                left: value(0),
+                right: value(1),
+                top: value(2),
+                bottom: value(3),
+                left_range: range(4),
+                right_range: range(6),
+                top_range: range(8),
+                bottom_range: range(10),
+            }),
+            n if n >= 4 => Some(Strut {
+                left: value(0),
+                right: value(1),
+                top: value(2),
+                bottom: value(3),
+                left_range: whole,
+                right_range: whole,
+                top_range: whole,
+                bottom_range: whole,
+            }),
+            _ => None,
+        }
+    }
+
+    // What is left of `monitor` once the space is reserved, struts are relative to the
+    // `screen` edges and only apply to the monitors they overlap
+    pub fn apply(&self, monitor: &Rect, screen: &Rect) -> Rect {
+        let overlaps = |(start, end): (i32, i32), from: i32, length: u32| {
+            start < from + length as i32 && end >= from
+        };
+        let (right_edge, bottom_edge) = (
+            screen.x + screen.width as i32,
+            screen.y + screen.height as i32,
+        );
+        let (monitor_right, monitor_bottom) = (
+            monitor.x + monitor.width as i32,
+            monitor.y + monitor.height as i32,
+        );
+
+        // Clients can ask for anything, no strut reserves more than the screen
+        let left = self.left.min(screen.width) as i32;
+        let right = self.right.min(screen.width) as i32;
+        let top = self.top.min(screen.height) as i32;
+        let bottom = self.bottom.min(screen.height) as i32;
+
+        let mut insets = [0; 4];
+        if left > 0 && overlaps(self.left_range, monitor.y, monitor.height) {
+            insets[0] = screen.x + left - monitor.x;
+        }
+        if top > 0 && overlaps(self.top_range, monitor.x, monitor.width) {
+            insets[1] = screen.y + top - monitor.y;
+        }
+        if right > 0 && overlaps(self.right_range, monitor.y, monitor.height) {
+            insets[2] = monitor_right - (right_edge - right);
+        }
+        if bottom > 0 && overlaps(self.bottom_range, monitor.x, monitor.width) {
+            insets[3] = monitor_bottom - (bottom_edge - bottom);
+        }
+
+        let [left, top, right, bottom] = insets.map(|inset| inset.max(0) as u32);
+        layout::shrink(monitor, left, top, right, bottom)
+    }
+}
+
+// Panel, bar or dock, left unframed and out of the layouts
+pub struct Dock {
+    pub id: WindowID,
+    pub strut: Strut,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn struts_only_shrink_the_monitors_they_touch() {
+        let screen = Rect::new(0, 0, 3840, 1080);
+        let left = Rect::new(0, 0, 1920, 1080);
+        let right = Rect::new(1920, 0, 1920, 1080);
+
+        // A 30 pixel bar along the top of the left monitor
+        let strut = Strut::from_values(&[0, 0, 30, 0, 0, 0, 0, 0, 0, 1919, 0, 0]).unwrap();
+        assert_eq!(strut.apply(&left, &screen), Rect::new(0, 30, 1920, 1050));
+        assert_eq!(strut.apply(&right, &screen), right);
+
+        // _NET_WM_STRUT reserves the whole edge
+        let strut = Strut::from_values(&[0, 40, 0, 0]).unwrap();
+        assert_eq!(strut.apply(&left, &screen), left);
+        assert_eq!(strut.apply(&right, &screen), Rect::new(1920, 0, 1880, 1080));
+    }
+
+    #[test]
+    fn huge_struts_are_clamped_to_the_screen() {
+        let screen = Rect::new(0, 0, 1920, 1080);
+        let strut = Strut::from_values(&[u64::MAX, u64::MAX, u64::MAX, u64::MAX]).unwrap();
+        assert_eq!(strut.apply(&screen, &screen), Rect::new(1920, 1080, 1, 1));
+    }
+}
@@ -1,5 +1,3 @@
-use std::error;
-use std::fmt;
 use x11::xlib;
 
 /**
@@ -21,24 +19,3 @@ pub extern "C" fn error_handler(_: *mut xlib::Display, err: *mut xlib::XErrorEve
 pub fn last_error() -> u8 {
     unsafe { LAST_ERROR_CODE }
 }
-
-#[derive(Debug)]
-pub struct Error {
-    message: String,
-}
-
-impl Error {
-    fn new(message: &str) -> Self {
-        Error {
-            message: message.to_string(),
-        }
-    }
-}
-
-impl error::Error for Error {}
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
-    }
-}
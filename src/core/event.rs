@@ -1,11 +1,9 @@
 use x11::xlib;
 
-pub type CreateWindowEvent = xlib::XCreateWindowEvent;
 pub type ConfigureEvent = xlib::XConfigureEvent;
-pub type ReparentEvent = xlib::XReparentEvent;
-pub type MapEvent = xlib::XMapEvent;
 pub type UnmapEvent = xlib::XUnmapEvent;
 pub type DestroyWindowEvent = xlib::XDestroyWindowEvent;
+pub type ExposeEvent = xlib::XExposeEvent;
 
 pub type ConfigureRequestEvent = xlib::XConfigureRequestEvent;
 pub type MapRequestEvent = xlib::XMapRequestEvent;
@@ -13,26 +11,36 @@ pub type MapRequestEvent = xlib::XMapRequestEvent;
 pub type KeyEvent = xlib::XKeyEvent;
 pub type ButtonEvent = xlib::XButtonEvent;
 pub type MotionEvent = xlib::XMotionEvent;
+pub type MappingEvent = xlib::XMappingEvent;
+pub type ClientMessageEvent = xlib::XClientMessageEvent;
+pub type PropertyEvent = xlib::XPropertyEvent;
+pub type FocusChangeEvent = xlib::XFocusChangeEvent;
 
 pub enum Event {
     // Notify
-    CreateNotify(CreateWindowEvent),
+    CreateNotify,
     ConfigureNotify(ConfigureEvent),
-    ReparentNotify(ReparentEvent),
-    MapNotify(MapEvent),
+    ReparentNotify,
+    MapNotify,
     UnmapNotify(UnmapEvent),
     DestroyNotify(DestroyWindowEvent),
+    MappingNotify(MappingEvent),
+    ScreenChange,
+    Expose(ExposeEvent),
+    PropertyNotify(PropertyEvent),
+    FocusIn(FocusChangeEvent),
 
     // Request
     ConfigureRequest(ConfigureRequestEvent),
     MapRequest(MapRequestEvent),
+    ClientMessage(ClientMessageEvent),
 
     // Keys
     KeyPress(KeyEvent),
-    KeyRelease(KeyEvent),
+    KeyRelease,
     ButtonPress(ButtonEvent),
     ButtonRelease(ButtonEvent),
-    MotionNotify(ButtonEvent, MotionEvent),
+    MotionNotify(MotionEvent),
     Unknown,
 }
 
@@ -40,21 +48,26 @@ impl From<xlib::XEvent> for Event {
     fn from(event: xlib::XEvent) -> Self {
         unsafe {
             match event.get_type() {
-                xlib::CreateNotify => Self::CreateNotify(event.create_window),
+                xlib::CreateNotify => Self::CreateNotify,
                 xlib::ConfigureNotify => Self::ConfigureNotify(event.configure),
-                xlib::ReparentNotify => Self::ReparentNotify(event.reparent),
-                xlib::MapNotify => Self::MapNotify(event.map),
+                xlib::ReparentNotify => Self::ReparentNotify,
+                xlib::MapNotify => Self::MapNotify,
                 xlib::UnmapNotify => Self::UnmapNotify(event.unmap),
                 xlib::DestroyNotify => Self::DestroyNotify(event.destroy_window),
+                xlib::MappingNotify => Self::MappingNotify(event.mapping),
+                xlib::Expose => Self::Expose(event.expose),
+                xlib::PropertyNotify => Self::PropertyNotify(event.property),
+                xlib::FocusIn => Self::FocusIn(event.focus_change),
 
                 xlib::ConfigureRequest => Self::ConfigureRequest(event.configure_request),
                 xlib::MapRequest => Self::MapRequest(event.map_request),
+                xlib::ClientMessage => Self::ClientMessage(event.client_message),
 
                 xlib::KeyPress => Self::KeyPress(event.key),
-                xlib::KeyRelease => Self::KeyRelease(event.key),
+                xlib::KeyRelease => Self::KeyRelease,
                 xlib::ButtonPress => Self::ButtonPress(event.button),
                 xlib::ButtonRelease => Self::ButtonRelease(event.button),
-                xlib::MotionNotify => Self::MotionNotify(event.button, event.motion),
+                xlib::MotionNotify => Self::MotionNotify(event.motion),
                 _ => Self::Unknown,
             }
         }
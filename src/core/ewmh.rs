@@ -0,0 +1,171 @@
+use std::rc::Rc;
+
+use crate::core::{
+    atom::Atoms,
+    window::{Rect, WindowID},
+    x,
+};
+
+// What pagers and bars read from the root window
+#[derive(Default, Clone, PartialEq)]
+pub struct State {
+    // In mapping order
+    pub clients: Vec<WindowID>,
+    // Bottom to top
+    pub stacking: Vec<WindowID>,
+    pub active: Option<WindowID>,
+    pub desktops: Vec<String>,
+    pub current_desktop: usize,
+    // One per desktop
+    pub workarea: Vec<Rect>,
+    // Desktop of each client, set on the client itself
+    pub client_desktops: Vec<(WindowID, u64)>,
+    // _NET_WM_STATE atoms of each client
+    pub client_states: Vec<(WindowID, Vec<x::Atom>)>,
+}
+
+// Root window properties, only written when they change
+pub struct Ewmh {
+    display: Rc<x::Display>,
+    atoms: Atoms,
+
+    // Child window proving a compliant window manager is running
+    check: WindowID,
+    state: Option<State>,
+}
+
+impl Ewmh {
+    pub fn new(display: &Rc<x::Display>, atoms: Atoms) -> Self {
+        let root = display.root();
+        let check = display.create_simple_window(root, -1, -1, 1, 1, 0, 0, 0);
+
+        display.set_property(root, atoms.net_supporting_wm_check, atoms.window, &[check]);
+        display.set_property(check, atoms.net_supporting_wm_check, atoms.window, &[check]);
+        display.set_text_property(check, atoms.net_wm_name, atoms.utf8_string, b"rwm");
+        display.set_property(root, atoms.net_supported, atoms.atom, &atoms.supported());
+
+        Ewmh {
+            display: Rc::clone(display),
+            atoms,
+            check,
+            state: None,
+        }
+    }
+
+    pub fn update(&mut self, state: State) {
+        let old = self.state.take();
+        let (root, atoms) = (self.display.root(), &self.atoms);
+
+        if old.as_ref().is_none_or(|old| old.clients != state.clients) {
+            self.display
+                .set_property(root, atoms.net_client_list, atoms.window, &state.clients);
+        }
+        if old
+            .as_ref()
+            .is_none_or(|old| old.stacking != state.stacking)
+        {
+            self.display.set_property(
+                root,
+                atoms.net_client_list_stacking,
+                atoms.window,
+                &state.stacking,
+            );
+        }
+        if old.as_ref().is_none_or(|old| old.active != state.active) {
+            self.display.set_property(
+                root,
+                atoms.net_active_window,
+                atoms.window,
+                &[state.active.unwrap_or(0)],
+            );
+        }
+        if old
+            .as_ref()
+            .is_none_or(|old| old.desktops != state.desktops)
+        {
+            self.display.set_property(
+                root,
+                atoms.net_number_of_desktops,
+                atoms.cardinal,
+                &[state.desktops.len() as u64],
+            );
+
+            // Null separated and terminated
+            let mut names = Vec::new();
+            for name in state.desktops.iter() {
+                names.extend_from_slice(name.as_bytes());
+                names.push(0);
+            }
+            self.display.set_text_property(
+                root,
+                atoms.net_desktop_names,
+                atoms.utf8_string,
+                &names,
+            );
+        }
+        if old
+            .as_ref()
+            .is_none_or(|old| old.current_desktop != state.current_desktop)
+        {
+            self.display.set_property(
+                root,
+                atoms.net_current_desktop,
+                atoms.cardinal,
+                &[state.current_desktop as u64],
+            );
+        }
+        if old
+            .as_ref()
+            .is_none_or(|old| old.workarea != state.workarea)
+        {
+            let values: Vec<u64> = state
+                .workarea
+                .iter()
+                .flat_map(|rect| {
+                    [
+                        rect.x as u64,
+                        rect.y as u64,
+                        rect.width as u64,
+                        rect.height as u64,
+                    ]
+                })
+                .collect();
+            self.display
+                .set_property(root, atoms.net_workarea, atoms.cardinal, &values);
+        }
+
+        for (win, desktop) in state.client_desktops.iter() {
+            let known = old
+                .as_ref()
+                .is_some_and(|old| old.client_desktops.contains(&(*win, *desktop)));
+            if !known {
+                self.display
+                    .set_property(*win, atoms.net_wm_desktop, atoms.cardinal, &[*desktop]);
+            }
+        }
+
+        for (win, states) in state.client_states.iter() {
+            let known = old.as_ref().is_some_and(|old| {
+                old.client_states
+                    .iter()
+                    .any(|(id, old)| id == win && old == states)
+            });
+            if !known {
+                self.display
+                    .set_property(*win, atoms.net_wm_state, atoms.atom, states);
+            }
+        }
+
+        self.state = Some(state);
+    }
+}
+
+impl Drop for Ewmh {
+    fn drop(&mut self) {
+        let root = self.display.root();
+        for property in [self.atoms.net_supporting_wm_check, self.atoms.net_supported] {
+            self.display.delete_property(root, property);
+        }
+        self.display.destroy_window(self.check);
+    }
+}
@@ -0,0 +1,100 @@
+use x11::xlib;
+
+use crate::core::{action::Action, event, window, x};
+
+pub type KeySym = u64;
+pub type KeyCode = u8;
+
+const MODIFIERS_MASK: u32 = xlib::ShiftMask
+    | xlib::ControlMask
+    | xlib::Mod1Mask
+    | xlib::Mod2Mask
+    | xlib::Mod3Mask
+    | xlib::Mod4Mask
+    | xlib::Mod5Mask;
+
+pub struct KeyBinding {
+    pub modifiers: u32,
+    pub keysym: KeySym,
+    pub action: Action,
+}
+
+impl KeyBinding {
+    pub fn new(modifiers: u32, keysym: u32, action: Action) -> Self {
+        KeyBinding {
+            modifiers,
+            keysym: keysym as KeySym,
+            action,
+        }
+    }
+}
+
+pub struct KeyBindings {
+    bindings: Vec<KeyBinding>,
+    numlock_mask: u32,
+}
+
+impl KeyBindings {
+    pub fn new(bindings: Vec<KeyBinding>) -> Self {
+        KeyBindings {
+            bindings,
+            numlock_mask: 0,
+        }
+    }
+
+    // Keycodes depend on the current keyboard mapping, so this must be called again
+    // after every MappingNotify
+    pub fn grab(&mut self, display: &x::Display, w: window::WindowID) {
+        display.ungrab_key(xlib::AnyKey, xlib::AnyModifier, w);
+        self.numlock_mask = display.numlock_mask();
+
+        for binding in self.bindings.iter() {
+            let keycode = display.keysym_to_keycode(binding.keysym);
+            if keycode == 0 {
+                continue;
+            }
+
+            for modifiers in lock_variants(binding.modifiers, self.numlock_mask).iter() {
+                display.grab_key(
+                    keycode as i32,
+                    *modifiers,
+                    w,
+                    1,
+                    xlib::GrabModeAsync,
+                    xlib::GrabModeAsync,
+                );
+            }
+        }
+    }
+
+    pub fn find(&self, display: &x::Display, event: &event::KeyEvent) -> Option<&Action> {
+        let keysym = display.keycode_to_keysym(event.keycode as KeyCode);
+        let state = clean_mask(event.state, self.numlock_mask);
+
+        self.bindings
+            .iter()
+            .find(|binding| {
+                binding.keysym == keysym
+                    && clean_mask(binding.modifiers, self.numlock_mask) == state
+            })
+            .map(|binding| &binding.action)
+    }
+
+    pub fn numlock_mask(&self) -> u32 {
+        self.numlock_mask
+    }
+}
+
+// Same modifiers with every combination of NumLock and CapsLock
+pub fn lock_variants(modifiers: u32, numlock_mask: u32) -> [u32; 4] {
+    [
+        modifiers,
+        modifiers | xlib::LockMask,
+        modifiers | numlock_mask,
+        modifiers | xlib::LockMask | numlock_mask,
+    ]
+}
+
+pub fn clean_mask(mask: u32, numlock_mask: u32) -> u32 {
+    mask & !(numlock_mask | xlib::LockMask) & MODIFIERS_MASK
+}
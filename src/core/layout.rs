@@ -1,37 +1,598 @@
-use crate::core::node;
-use std::iter::Iterator;
+use crate::core::{container, node::NodeID, window::Rect};
 
+// Layouts only compute geometry: given the area to fill and the windows in it, they return
+// one rectangle per window, in order. The window manager applies them.
 pub trait Layout {
-    fn apply(&self, windows: &mut dyn Iterator<Item = &mut Box<dyn node::Node>>);
+    fn name(&self) -> &'static str;
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], params: &Params) -> Vec<Rect>;
+
+    // Windows are piled on top of each other and only the focused one is visible
+    fn is_stacked(&self) -> bool {
+        false
+    }
+
+    // Tab strips to draw, for layouts with tabbed or stacked containers
+    fn tabs(&self, _: &Rect, _: &Params) -> Vec<container::Tabs> {
+        Vec::new()
+    }
 }
 
-pub struct ColumnLayout(pub u32, pub u32);
-impl Layout for ColumnLayout {
-    fn apply(&self, windows: &mut dyn Iterator<Item = &mut Box<dyn node::Node>>) {
-        if let (_, Some(mut len)) = windows.size_hint() {
-            len = if len > 0 { len } else { 1 };
-            let (w, h) = (self.0 / len as u32, self.1);
-            for (i, win) in windows.enumerate() {
-                let i = i as i32;
-                win.set_size(w, h);
-                win.set_position(i * w as i32, 0);
-            }
+pub const MIN_MASTER_FACTOR: f32 = 0.05;
+pub const MAX_MASTER_FACTOR: f32 = 0.95;
+
+// Largest inner or outer gap, in pixels
+pub const MAX_GAP: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gaps {
+    // Between windows
+    pub inner: u32,
+    // Between windows and the edges of the screen
+    pub outer: u32,
+    // Neither gaps nor borders when a single window is visible
+    pub smart: bool,
+}
+
+// Settings kept per workspace and adjusted at runtime
+#[derive(Debug, Clone)]
+pub struct Params {
+    // Fraction of the width taken by the master area
+    pub master_factor: f32,
+    pub master_count: usize,
+
+    pub gaps: Gaps,
+
+    // Manual splits of the tiled windows
+    pub tree: container::Tree,
+}
+
+impl Params {
+    pub fn new(master_factor: f32, master_count: usize, gaps: Gaps) -> Self {
+        Params {
+            master_factor: master_factor.clamp(MIN_MASTER_FACTOR, MAX_MASTER_FACTOR),
+            master_count,
+            gaps,
+            tree: container::Tree::default(),
         }
     }
+
+    pub fn resize_master(&mut self, delta: f32) {
+        self.master_factor =
+            (self.master_factor + delta).clamp(MIN_MASTER_FACTOR, MAX_MASTER_FACTOR);
+    }
+
+    pub fn add_master(&mut self, delta: isize) {
+        self.master_count = self.master_count.saturating_add_signed(delta);
+    }
+
+    pub fn resize_gaps(&mut self, inner: i32, outer: i32) {
+        self.gaps.inner = self.gaps.inner.saturating_add_signed(inner).min(MAX_GAP);
+        self.gaps.outer = self.gaps.outer.saturating_add_signed(outer).min(MAX_GAP);
+    }
+}
+
+pub const NAMES: &[&str] = &[
+    "master", "column", "row", "monocle", "grid", "spiral", "dwindle", "bsp",
+];
+
+pub fn from_name(name: &str) -> Option<Box<dyn Layout>> {
+    match name {
+        "master" => Some(Box::new(MasterLayout)),
+        "column" => Some(Box::new(ColumnLayout)),
+        "row" => Some(Box::new(RowLayout)),
+        "monocle" => Some(Box::new(MonocleLayout)),
+        "grid" => Some(Box::new(GridLayout)),
+        "spiral" => Some(Box::new(SpiralLayout)),
+        "dwindle" => Some(Box::new(DwindleLayout)),
+        "bsp" => Some(Box::new(BspLayout)),
+        _ => None,
+    }
+}
+
+// Master windows stacked on the left, the others stacked on the right
+pub struct MasterLayout;
+
+impl Layout for MasterLayout {
+    fn name(&self) -> &'static str {
+        "master"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], params: &Params) -> Vec<Rect> {
+        let count = windows.len();
+        let masters = params.master_count.min(count);
+        if masters == 0 || masters == count {
+            return rows(area, count);
+        }
+
+        let width = (area.width as f32 * params.master_factor) as u32;
+        let master = Rect::new(area.x, area.y, width, area.height);
+        let stack = Rect::new(
+            area.x + width as i32,
+            area.y,
+            area.width - width,
+            area.height,
+        );
+
+        let mut rects = rows(&master, masters);
+        rects.extend(rows(&stack, count - masters));
+        rects
+    }
 }
 
-pub struct RowLayout(pub u32, pub u32);
+pub struct ColumnLayout;
+
+impl Layout for ColumnLayout {
+    fn name(&self) -> &'static str {
+        "column"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], _: &Params) -> Vec<Rect> {
+        columns(area, windows.len())
+    }
+}
+
+pub struct RowLayout;
 
 impl Layout for RowLayout {
-    fn apply(&self, windows: &mut dyn Iterator<Item = &mut Box<dyn node::Node>>) {
-        if let (_, Some(mut len)) = windows.size_hint() {
-            len = if len > 0 { len } else { 1 };
-            let (w, h) = (self.0, self.1 / len as u32);
-            for (i, win) in windows.enumerate() {
-                let i = i as i32;
-                win.set_size(w, h);
-                win.set_position(0, i * h as i32);
+    fn name(&self) -> &'static str {
+        "row"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], _: &Params) -> Vec<Rect> {
+        rows(area, windows.len())
+    }
+}
+
+// Every window takes the whole area
+pub struct MonocleLayout;
+
+impl Layout for MonocleLayout {
+    fn name(&self) -> &'static str {
+        "monocle"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], _: &Params) -> Vec<Rect> {
+        vec![*area; windows.len()]
+    }
+
+    fn is_stacked(&self) -> bool {
+        true
+    }
+}
+
+// As many columns as rows or one more, the last row is stretched over the whole width
+pub struct GridLayout;
+
+impl Layout for GridLayout {
+    fn name(&self) -> &'static str {
+        "grid"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], _: &Params) -> Vec<Rect> {
+        let count = windows.len();
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let mut cols = 1;
+        while cols * cols < count {
+            cols += 1;
+        }
+        let lines = count.div_ceil(cols);
+
+        rows(area, lines)
+            .iter()
+            .enumerate()
+            .flat_map(|(i, line)| columns(line, cols.min(count - i * cols)))
+            .collect()
+    }
+}
+
+// Each window takes half of the space left, turning around the screen towards the center
+pub struct SpiralLayout;
+
+impl Layout for SpiralLayout {
+    fn name(&self) -> &'static str {
+        "spiral"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], _: &Params) -> Vec<Rect> {
+        fibonacci(area, windows.len(), true)
+    }
+}
+
+// Each window takes half of the space left, always towards the bottom right corner
+pub struct DwindleLayout;
+
+impl Layout for DwindleLayout {
+    fn name(&self) -> &'static str {
+        "dwindle"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], _: &Params) -> Vec<Rect> {
+        fibonacci(area, windows.len(), false)
+    }
+}
+
+// Splits alternate between vertical and horizontal, the last window gets what is left
+fn fibonacci(area: &Rect, count: usize, spiral: bool) -> Vec<Rect> {
+    let mut rects = Vec::with_capacity(count);
+    let mut left = *area;
+
+    for i in 0..count {
+        if i == count - 1 {
+            rects.push(left);
+            break;
+        }
+
+        let halves = if i % 2 == 0 {
+            columns(&left, 2)
+        } else {
+            rows(&left, 2)
+        };
+
+        // The spiral goes right, down, left then up
+        let (win, rest) = if spiral && i % 4 >= 2 {
+            (halves[1], halves[0])
+        } else {
+            (halves[0], halves[1])
+        };
+        rects.push(win);
+        left = rest;
+    }
+
+    rects
+}
+
+// Windows are placed by the workspace's container tree
+pub struct BspLayout;
+
+impl Layout for BspLayout {
+    fn name(&self) -> &'static str {
+        "bsp"
+    }
+
+    fn arrange(&self, area: &Rect, windows: &[NodeID], params: &Params) -> Vec<Rect> {
+        let rects = params.tree.arrange(area);
+
+        windows
+            .iter()
+            .map(|win| {
+                rects
+                    .iter()
+                    .find(|(id, _)| id == win)
+                    .map_or(*area, |(_, rect)| *rect)
+            })
+            .collect()
+    }
+
+    fn tabs(&self, area: &Rect, params: &Params) -> Vec<container::Tabs> {
+        params.tree.tabs(area)
+    }
+}
+
+// Split an area in `count` columns of the same width, the last one takes the remainder
+pub fn columns(area: &Rect, count: usize) -> Vec<Rect> {
+    let sizes = split(area.width, count);
+    let mut x = area.x;
+
+    sizes
+        .into_iter()
+        .map(|width| {
+            let rect = Rect::new(x, area.y, width, area.height);
+            x += width as i32;
+            rect
+        })
+        .collect()
+}
+
+// Split an area in `count` rows of the same height, the last one takes the remainder
+pub fn rows(area: &Rect, count: usize) -> Vec<Rect> {
+    let sizes = split(area.height, count);
+    let mut y = area.y;
+
+    sizes
+        .into_iter()
+        .map(|height| {
+            let rect = Rect::new(area.x, y, area.width, height);
+            y += height as i32;
+            rect
+        })
+        .collect()
+}
+
+pub fn shrink(area: &Rect, left: u32, top: u32, right: u32, bottom: u32) -> Rect {
+    Rect::new(
+        area.x + left.min(area.width) as i32,
+        area.y + top.min(area.height) as i32,
+        area.width.saturating_sub(left.saturating_add(right)).max(1),
+        area.height
+            .saturating_sub(top.saturating_add(bottom))
+            .max(1),
+    )
+}
+
+// Moves rectangles laid out in `area` apart so that neighbours are `gap` pixels away from
+// each other, sides on the edges of the area don't move
+pub fn inner_gaps(rects: Vec<Rect>, area: &Rect, gap: u32) -> Vec<Rect> {
+    let (before, after) = (gap - gap / 2, gap / 2);
+
+    rects
+        .into_iter()
+        .map(|rect| {
+            let left = if rect.x > area.x { before } else { 0 };
+            let top = if rect.y > area.y { before } else { 0 };
+            let right = if rect.x.saturating_add(rect.width as i32)
+                < area.x.saturating_add(area.width as i32)
+            {
+                after
+            } else {
+                0
+            };
+            let bottom = if rect.y.saturating_add(rect.height as i32)
+                < area.y.saturating_add(area.height as i32)
+            {
+                after
+            } else {
+                0
+            };
+            shrink(&rect, left, top, right, bottom)
+        })
+        .collect()
+}
+
+fn split(length: u32, count: usize) -> Vec<u32> {
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let size = length / count as u32;
+    let mut sizes = vec![size; count];
+    sizes[count - 1] = length - size * (count as u32 - 1);
+
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_GAPS: Gaps = Gaps {
+        inner: 0,
+        outer: 0,
+        smart: false,
+    };
+
+    fn params(master_count: usize) -> Params {
+        Params::new(0.5, master_count, NO_GAPS)
+    }
+
+    fn ids(count: usize) -> Vec<NodeID> {
+        (1..=count as NodeID).collect()
+    }
+
+    fn area() -> Rect {
+        Rect::new(0, 0, 100, 90)
+    }
+
+    #[test]
+    fn split_gives_the_remainder_to_the_last() {
+        assert_eq!(split(100, 0), Vec::<u32>::new());
+        assert_eq!(split(100, 1), vec![100]);
+        assert_eq!(split(100, 3), vec![33, 33, 34]);
+        assert_eq!(split(2, 3), vec![0, 0, 2]);
+    }
+
+    #[test]
+    fn columns_and_rows() {
+        assert_eq!(
+            columns(&Rect::new(10, 20, 100, 50), 3),
+            vec![
+                Rect::new(10, 20, 33, 50),
+                Rect::new(43, 20, 33, 50),
+                Rect::new(76, 20, 34, 50),
+            ]
+        );
+        assert_eq!(
+            rows(&Rect::new(10, 20, 100, 50), 2),
+            vec![Rect::new(10, 20, 100, 25), Rect::new(10, 45, 100, 25)]
+        );
+        assert!(rows(&area(), 0).is_empty());
+    }
+
+    #[test]
+    fn shrink_keeps_at_least_one_pixel() {
+        let area = Rect::new(0, 0, 100, 100);
+        assert_eq!(shrink(&area, 10, 20, 30, 40), Rect::new(10, 20, 60, 40));
+        assert_eq!(shrink(&area, 80, 0, 80, 0), Rect::new(80, 0, 1, 100));
+        assert_eq!(shrink(&area, 200, 0, 0, 0), Rect::new(100, 0, 1, 100));
+        assert_eq!(
+            shrink(&area, u32::MAX, u32::MAX, u32::MAX, u32::MAX),
+            Rect::new(100, 100, 1, 1)
+        );
+    }
+
+    #[test]
+    fn inner_gaps_leave_the_edges_alone() {
+        let area = Rect::new(0, 0, 100, 50);
+        let rects = columns(&area, 2);
+
+        assert_eq!(inner_gaps(rects.clone(), &area, 0), rects);
+        assert_eq!(
+            inner_gaps(rects.clone(), &area, 10),
+            vec![Rect::new(0, 0, 45, 50), Rect::new(55, 0, 45, 50)]
+        );
+        // Odd gaps put the extra pixel after the split
+        assert_eq!(
+            inner_gaps(rects, &area, 5),
+            vec![Rect::new(0, 0, 48, 50), Rect::new(53, 0, 47, 50)]
+        );
+        assert_eq!(inner_gaps(vec![area], &area, 10), vec![area]);
+    }
+
+    #[test]
+    fn every_layout_handles_no_window_and_one_window() {
+        for name in NAMES {
+            let layout = from_name(name).unwrap();
+            assert!(
+                layout.arrange(&area(), &[], &params(1)).is_empty(),
+                "{}",
+                name
+            );
+            assert_eq!(
+                layout.arrange(&area(), &ids(1), &params(1)),
+                vec![area()],
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn every_layout_gives_one_rect_per_window() {
+        // More windows than pixels too
+        let tiny = Rect::new(0, 0, 2, 2);
+        for name in NAMES {
+            let layout = from_name(name).unwrap();
+            for count in [2, 3, 7] {
+                assert_eq!(
+                    layout.arrange(&area(), &ids(count), &params(1)).len(),
+                    count,
+                    "{}",
+                    name
+                );
+                assert_eq!(
+                    layout.arrange(&tiny, &ids(count), &params(1)).len(),
+                    count,
+                    "{}",
+                    name
+                );
             }
         }
     }
+
+    #[test]
+    fn master_splits_masters_and_stack() {
+        assert_eq!(
+            MasterLayout.arrange(&area(), &ids(3), &params(1)),
+            vec![
+                Rect::new(0, 0, 50, 90),
+                Rect::new(50, 0, 50, 45),
+                Rect::new(50, 45, 50, 45),
+            ]
+        );
+        assert_eq!(
+            MasterLayout.arrange(&area(), &ids(3), &params(2)),
+            vec![
+                Rect::new(0, 0, 50, 45),
+                Rect::new(0, 45, 50, 45),
+                Rect::new(50, 0, 50, 90),
+            ]
+        );
+    }
+
+    #[test]
+    fn master_without_stack_or_masters_uses_rows() {
+        let expected = rows(&area(), 3);
+        assert_eq!(MasterLayout.arrange(&area(), &ids(3), &params(0)), expected);
+        assert_eq!(MasterLayout.arrange(&area(), &ids(3), &params(3)), expected);
+        assert_eq!(MasterLayout.arrange(&area(), &ids(3), &params(5)), expected);
+    }
+
+    #[test]
+    fn column_row_and_monocle() {
+        assert_eq!(
+            ColumnLayout.arrange(&area(), &ids(3), &params(1)),
+            columns(&area(), 3)
+        );
+        assert_eq!(
+            RowLayout.arrange(&area(), &ids(3), &params(1)),
+            rows(&area(), 3)
+        );
+        assert_eq!(
+            MonocleLayout.arrange(&area(), &ids(3), &params(1)),
+            vec![area(); 3]
+        );
+        assert!(MonocleLayout.is_stacked());
+        assert!(!ColumnLayout.is_stacked());
+    }
+
+    #[test]
+    fn grid_stretches_the_last_row() {
+        let area = Rect::new(0, 0, 100, 100);
+        assert_eq!(
+            GridLayout.arrange(&area, &ids(5), &params(1)),
+            vec![
+                Rect::new(0, 0, 33, 50),
+                Rect::new(33, 0, 33, 50),
+                Rect::new(66, 0, 34, 50),
+                Rect::new(0, 50, 50, 50),
+                Rect::new(50, 50, 50, 50),
+            ]
+        );
+        assert_eq!(
+            GridLayout.arrange(&area, &ids(4), &params(1)),
+            vec![
+                Rect::new(0, 0, 50, 50),
+                Rect::new(50, 0, 50, 50),
+                Rect::new(0, 50, 50, 50),
+                Rect::new(50, 50, 50, 50),
+            ]
+        );
+    }
+
+    #[test]
+    fn spiral_turns_and_dwindle_does_not() {
+        let area = Rect::new(0, 0, 100, 100);
+        assert_eq!(
+            SpiralLayout.arrange(&area, &ids(4), &params(1)),
+            vec![
+                Rect::new(0, 0, 50, 100),
+                Rect::new(50, 0, 50, 50),
+                Rect::new(75, 50, 25, 50),
+                Rect::new(50, 50, 25, 50),
+            ]
+        );
+        assert_eq!(
+            DwindleLayout.arrange(&area, &ids(4), &params(1)),
+            vec![
+                Rect::new(0, 0, 50, 100),
+                Rect::new(50, 0, 50, 50),
+                Rect::new(50, 50, 25, 50),
+                Rect::new(75, 50, 25, 50),
+            ]
+        );
+    }
+
+    #[test]
+    fn bsp_follows_the_tree() {
+        let area = Rect::new(0, 0, 100, 50);
+        let mut params = params(1);
+        params.tree.insert(1, None, None);
+        params.tree.insert(2, Some(1), Some(&area));
+
+        assert_eq!(
+            BspLayout.arrange(&area, &[2, 1], &params),
+            vec![Rect::new(50, 0, 50, 50), Rect::new(0, 0, 50, 50)]
+        );
+        // Windows missing from the tree get the whole area
+        assert_eq!(BspLayout.arrange(&area, &[3], &params), vec![area]);
+    }
+
+    #[test]
+    fn params_stay_in_range() {
+        let mut params = params(1);
+        params.resize_master(1.0);
+        assert_eq!(params.master_factor, MAX_MASTER_FACTOR);
+        params.resize_master(-2.0);
+        assert_eq!(params.master_factor, MIN_MASTER_FACTOR);
+
+        params.add_master(-5);
+        assert_eq!(params.master_count, 0);
+        params.resize_gaps(-4, 3);
+        assert_eq!((params.gaps.inner, params.gaps.outer), (0, 3));
+        params.resize_gaps(i32::MAX, 0);
+        assert_eq!(params.gaps.inner, MAX_GAP);
+    }
 }
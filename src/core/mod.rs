@@ -1,10 +1,20 @@
+pub mod action;
+pub mod atom;
 pub mod config;
+pub mod container;
 pub mod cursor;
+pub mod dock;
 pub mod error;
 pub mod event;
+pub mod ewmh;
+pub mod key;
 pub mod layout;
+pub mod monitor;
+pub mod mouse;
 pub mod node;
 pub mod server;
+pub mod tabs;
 pub mod window;
 pub mod window_manager;
+pub mod workspace;
 pub mod x;
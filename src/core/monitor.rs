@@ -0,0 +1,13 @@
+use crate::core::window::Rect;
+
+// Physical output showing exactly one workspace
+pub struct Monitor {
+    pub rect: Rect,
+    pub workspace: usize,
+}
+
+impl Monitor {
+    pub fn new(rect: Rect, workspace: usize) -> Self {
+        Monitor { rect, workspace }
+    }
+}
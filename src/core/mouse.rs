@@ -0,0 +1,242 @@
+use x11::xlib;
+
+use crate::core::{
+    event, key,
+    window::{self, Position, Size},
+    x,
+};
+
+// Smallest frame a resize can shrink a window to
+const MIN_SIZE: u32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DragKind {
+    Move,
+    Resize,
+}
+
+// Edges a resize moves, -1 for the left or top one, 1 for the right or bottom one and
+// 0 to keep both in place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grip {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Grip {
+    pub const BOTTOM_RIGHT: Grip = Grip { x: 1, y: 1 };
+
+    // _NET_WM_MOVERESIZE directions, 0 to 7 go clockwise from the top left corner and
+    // a keyboard resize grows from the bottom right one
+    pub fn from_direction(direction: i64) -> Self {
+        let (x, y) = match direction {
+            0 => (-1, -1),
+            1 => (0, -1),
+            2 => (1, -1),
+            3 => (1, 0),
+            5 => (0, 1),
+            6 => (-1, 1),
+            7 => (-1, 0),
+            _ => (1, 1),
+        };
+        Grip { x, y }
+    }
+}
+
+pub struct ButtonBinding {
+    pub modifiers: u32,
+    pub button: u32,
+    pub kind: DragKind,
+}
+
+impl ButtonBinding {
+    pub fn new(modifiers: u32, button: u32, kind: DragKind) -> Self {
+        ButtonBinding {
+            modifiers,
+            button,
+            kind,
+        }
+    }
+}
+
+pub struct ButtonBindings {
+    bindings: Vec<ButtonBinding>,
+    numlock_mask: u32,
+}
+
+impl ButtonBindings {
+    pub fn new(bindings: Vec<ButtonBinding>) -> Self {
+        ButtonBindings {
+            bindings,
+            numlock_mask: 0,
+        }
+    }
+
+    pub fn grab(&mut self, display: &x::Display, w: window::WindowID, numlock_mask: u32) {
+        display.ungrab_button(xlib::AnyButton as u32, xlib::AnyModifier, w);
+        self.numlock_mask = numlock_mask;
+
+        for binding in self.bindings.iter() {
+            for modifiers in key::lock_variants(binding.modifiers, numlock_mask).iter() {
+                display.grab_button(
+                    binding.button,
+                    *modifiers,
+                    w,
+                    0,
+                    xlib::ButtonPressMask as u32,
+                    xlib::GrabModeAsync,
+                    xlib::GrabModeAsync,
+                    0,
+                    0,
+                );
+            }
+        }
+    }
+
+    pub fn find(&self, event: &event::ButtonEvent) -> Option<DragKind> {
+        let state = key::clean_mask(event.state, self.numlock_mask);
+
+        self.bindings
+            .iter()
+            .find(|binding| {
+                binding.button == event.button
+                    && key::clean_mask(binding.modifiers, self.numlock_mask) == state
+            })
+            .map(|binding| binding.kind)
+    }
+}
+
+// Pointer drag in progress, geometry is relative to where it started
+pub struct Drag {
+    pub window: window::WindowID,
+    pub kind: DragKind,
+    grip: Grip,
+    pointer: Position,
+    position: Position,
+    size: Size,
+}
+
+impl Drag {
+    pub fn new(
+        window: window::WindowID,
+        kind: DragKind,
+        grip: Grip,
+        pointer: Position,
+        position: Position,
+        size: Size,
+    ) -> Self {
+        Drag {
+            window,
+            kind,
+            grip,
+            pointer,
+            position,
+            size,
+        }
+    }
+
+    pub fn position(&self, motion: &event::MotionEvent) -> Position {
+        Position {
+            x: self.position.x + motion.x_root - self.pointer.x,
+            y: self.position.y + motion.y_root - self.pointer.y,
+        }
+    }
+
+    pub fn size(&self, motion: &event::MotionEvent) -> Size {
+        let width = self.size.width as i32 + self.grip.x * (motion.x_root - self.pointer.x);
+        let height = self.size.height as i32 + self.grip.y * (motion.y_root - self.pointer.y);
+
+        Size {
+            width: (width.max(0) as u32).max(MIN_SIZE),
+            height: (height.max(0) as u32).max(MIN_SIZE),
+        }
+    }
+
+    // Where a resized window goes so that the edges opposite the grip stay in place
+    pub fn anchor(&self, size: Size) -> Position {
+        let shift = |grip: i32, from: u32, to: u32| {
+            if grip < 0 {
+                from as i32 - to as i32
+            } else {
+                0
+            }
+        };
+
+        Position {
+            x: self.position.x + shift(self.grip.x, self.size.width, size.width),
+            y: self.position.y + shift(self.grip.y, self.size.height, size.height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drag(direction: i64) -> Drag {
+        Drag::new(
+            1,
+            DragKind::Resize,
+            Grip::from_direction(direction),
+            Position { x: 500, y: 500 },
+            Position { x: 100, y: 100 },
+            Size {
+                width: 400,
+                height: 300,
+            },
+        )
+    }
+
+    fn motion(x: i32, y: i32) -> event::MotionEvent {
+        let mut motion: event::MotionEvent = unsafe { std::mem::zeroed() };
+        motion.x_root = x;
+        motion.y_root = y;
+        motion
+    }
+
+    fn resize(drag: &Drag, x: i32, y: i32) -> (Position, Size) {
+        let size = drag.size(&motion(x, y));
+        (drag.anchor(size), size)
+    }
+
+    #[test]
+    fn bottom_right_keeps_position() {
+        let (position, size) = resize(&drag(4), 550, 480);
+        assert_eq!((position.x, position.y), (100, 100));
+        assert_eq!((size.width, size.height), (450, 280));
+        assert_eq!(Grip::from_direction(9), Grip::BOTTOM_RIGHT);
+    }
+
+    #[test]
+    fn top_left_keeps_bottom_right_corner() {
+        let (position, size) = resize(&drag(0), 450, 520);
+        assert_eq!((position.x, position.y), (50, 120));
+        assert_eq!((size.width, size.height), (450, 280));
+    }
+
+    #[test]
+    fn sides_resize_one_dimension() {
+        let (position, size) = resize(&drag(1), 600, 450);
+        assert_eq!((position.x, position.y), (100, 50));
+        assert_eq!((size.width, size.height), (400, 350));
+
+        let (position, size) = resize(&drag(3), 600, 450);
+        assert_eq!((position.x, position.y), (100, 100));
+        assert_eq!((size.width, size.height), (500, 300));
+
+        let (position, size) = resize(&drag(5), 600, 450);
+        assert_eq!((position.x, position.y), (100, 100));
+        assert_eq!((size.width, size.height), (400, 250));
+
+        let (position, size) = resize(&drag(7), 600, 450);
+        assert_eq!((position.x, position.y), (200, 100));
+        assert_eq!((size.width, size.height), (300, 300));
+    }
+
+    #[test]
+    fn shrinking_stops_at_min_size() {
+        let (position, size) = resize(&drag(6), 1000, 0);
+        assert_eq!((size.width, size.height), (MIN_SIZE, MIN_SIZE));
+        assert_eq!((position.x, position.y), (100 + 400 - MIN_SIZE as i32, 100));
+    }
+}
@@ -1,20 +1,62 @@
-pub use crate::core::window;
+pub use crate::core::{config, window};
 
 pub type NodeID = u64;
 
+// _NET_WM_STATE flags rwm acts on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct State {
+    pub fullscreen: bool,
+    pub above: bool,
+    pub below: bool,
+    pub sticky: bool,
+    pub hidden: bool,
+    pub urgent: bool,
+}
+
 pub trait Node {
     fn id(&self) -> NodeID;
     fn is(&self, id: NodeID) -> bool;
 
+    fn frame(&self) -> window::WindowID;
+
+    fn position(&self) -> window::Position;
+    fn size(&self) -> window::Size;
     fn set_position(&mut self, x: i32, y: i32);
     fn set_size(&mut self, width: u32, height: u32);
 
+    // Outer size closest to the given one that WM_NORMAL_HINTS allow
+    fn constrain(&self, width: u32, height: u32) -> (u32, u32);
+    fn update_size_hints(&mut self);
+    // Synthetic ConfigureNotify with the geometry of the client inside its frame
+    fn configure_notify(&self);
+
+    // Parent of dialogs, from WM_TRANSIENT_FOR
+    fn transient_for(&self) -> Option<NodeID>;
+
+    fn is_floating(&self) -> bool;
+    fn set_floating(&mut self, floating: bool);
+
+    fn state(&self) -> State;
+    fn set_state(&mut self, state: State);
+
+    // Placed by the layout
+    fn is_tiled(&self) -> bool {
+        let state = self.state();
+        !self.is_floating() && !state.fullscreen && !state.hidden
+    }
+
+    fn set_style(&mut self, style: config::Style);
+    fn set_borderless(&mut self, borderless: bool);
+    fn border_width(&self) -> u32;
+
     fn focus(&mut self);
     fn unfocus(&mut self);
 
-    fn mark(&mut self);
-    fn unmark(&mut self);
-
     fn map(&self);
-    fn reparent(&self, parent: window::WindowID);
+    fn show(&self);
+    fn hide(&self);
+    // Whether an UnmapNotify of the client comes from `hide`, each one is expected once
+    fn unmap_expected(&self) -> bool;
+    // The client window is gone, only the frame is left to clean up
+    fn set_destroyed(&mut self);
 }
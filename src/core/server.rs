@@ -1,9 +1,149 @@
-pub struct Server {}
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    os::unix::{fs::PermissionsExt, net},
+    path::PathBuf,
+    sync::mpsc,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixListener,
+    signal::unix::{signal, SignalKind},
+    sync::oneshot,
+};
+
+use crate::core::action::Action;
+
+pub type Reply = Result<String, String>;
+
+// Action sent to the window manager from outside the X event loop
+pub struct Request {
+    pub action: Action,
+    pub reply: Option<oneshot::Sender<Reply>>,
+}
+
+impl Request {
+    // Requests nobody waits for, like reloads on SIGHUP, report their errors themselves
+    pub fn respond(self, reply: Reply) {
+        match self.reply {
+            Some(tx) => {
+                let _ = tx.send(reply);
+            }
+            None => {
+                if let Err(err) = reply {
+                    eprintln!("{}", err);
+                }
+            }
+        }
+    }
+}
+
+// IPC over a Unix socket: one action per line, answered with `ok [...]` or `error ...`
+pub struct Server {
+    path: PathBuf,
+    requests: mpsc::Sender<Request>,
+}
 
 impl Server {
-    fn new() -> Self {
-        Server {}
+    pub fn new(requests: mpsc::Sender<Request>) -> Self {
+        Server {
+            path: Server::path(),
+            requests,
+        }
+    }
+
+    // $XDG_RUNTIME_DIR/rwm-$DISPLAY.sock, so every X server gets its own socket
+    pub fn path() -> PathBuf {
+        let dir = env::var_os("XDG_RUNTIME_DIR")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(env::temp_dir);
+        let display = env::var("DISPLAY").unwrap_or_default().replace('/', "_");
+
+        dir.join(format!("rwm-{}.sock", display))
+    }
+
+    pub async fn start(self) -> io::Result<()> {
+        // A socket nobody answers on was left behind by a previous instance
+        if net::UnixStream::connect(&self.path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is used by another instance", self.path.display()),
+            ));
+        }
+        let _ = fs::remove_file(&self.path);
+        let mut listener = UnixListener::bind(&self.path)?;
+
+        // Only the user may send actions, the socket can end up in a shared directory
+        fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+
+        loop {
+            let (mut stream, _) = listener.accept().await?;
+            let requests = self.requests.clone();
+
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.split();
+                let mut lines = BufReader::new(reader).lines();
+
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let reply = match line.parse() {
+                        Ok(action) => {
+                            let (tx, rx) = oneshot::channel();
+                            let request = Request {
+                                action,
+                                reply: Some(tx),
+                            };
+                            if requests.send(request).is_err() {
+                                break;
+                            }
+                            rx.await.unwrap_or_else(|_| Err("no reply".to_string()))
+                        }
+                        Err(err) => Err(err),
+                    };
+
+                    let response = match reply {
+                        Ok(msg) if msg.is_empty() => "ok\n".to_string(),
+                        Ok(msg) => format!("ok {}\n", msg),
+                        Err(err) => format!("error {}\n", err),
+                    };
+                    if writer.write_all(response.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+}
+
+// SIGHUP reloads the configuration
+pub async fn watch_signals(requests: mpsc::Sender<Request>) -> io::Result<()> {
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    while hangup.recv().await.is_some() {
+        let request = Request {
+            action: Action::Reload,
+            reply: None,
+        };
+        if requests.send(request).is_err() {
+            break;
+        }
     }
 
-    fn start(&self) {}
+    Ok(())
+}
+
+// Client side, used by `rwm msg <action>`
+pub fn send(message: &str) -> Result<String, String> {
+    let path = Server::path();
+    let mut stream =
+        net::UnixStream::connect(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    writeln!(stream, "{}", message).map_err(|err| err.to_string())?;
+
+    let mut response = String::new();
+    io::BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|err| err.to_string())?;
+
+    Ok(response.trim_end().to_string())
 }
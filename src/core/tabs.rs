@@ -0,0 +1,159 @@
+use std::rc::Rc;
+use x11::xlib;
+
+use crate::core::{
+    config,
+    container::{self, Mode},
+    layout,
+    node::NodeID,
+    window::{Rect, WindowID},
+    x,
+};
+
+const FONT: &str = "fixed";
+const PADDING: i32 = 4;
+
+// Window drawn by rwm above a tabbed or stacked container, one tab per window
+pub struct TabStrip {
+    display: Rc<x::Display>,
+    id: WindowID,
+    gc: x::GC,
+    font: Option<x::Font>,
+
+    tabs: container::Tabs,
+    titles: Vec<String>,
+    style: config::Style,
+}
+
+impl TabStrip {
+    pub fn new(display: &Rc<x::Display>, tabs: container::Tabs, style: config::Style) -> Self {
+        let rect = tabs.rect;
+        let id = display.create_simple_window(
+            display.root(),
+            rect.x,
+            rect.y,
+            rect.width.max(1),
+            rect.height.max(1),
+            0,
+            style.border_color,
+            style.background,
+        );
+        display.select_events(id, xlib::ExposureMask | xlib::ButtonPressMask);
+
+        let gc = display.create_gc(id);
+        let font = display.load_font(FONT);
+        if let Some(font) = font {
+            display.set_font(gc, font);
+        }
+
+        let mut strip = TabStrip {
+            display: Rc::clone(display),
+            id,
+            gc,
+            font,
+            tabs,
+            titles: Vec::new(),
+            style,
+        };
+        strip.update(strip.tabs.clone(), style);
+        strip.display.map_window(id);
+
+        strip
+    }
+
+    pub fn id(&self) -> WindowID {
+        self.id
+    }
+
+    pub fn active(&self) -> NodeID {
+        self.tabs.active
+    }
+
+    pub fn update(&mut self, tabs: container::Tabs, style: config::Style) {
+        let rect = tabs.rect;
+        self.display.move_window(self.id, rect.x, rect.y);
+        self.display
+            .resize_window(self.id, rect.width.max(1), rect.height.max(1));
+
+        // Titles change while windows are open, read them again
+        self.titles = tabs
+            .windows
+            .iter()
+            .map(|win| self.display.fetch_name(*win).unwrap_or_default())
+            .collect();
+        self.tabs = tabs;
+        self.style = style;
+
+        self.draw();
+    }
+
+    // Window whose tab is at the given position, relative to the strip
+    pub fn tab_at(&self, x: i32, y: i32) -> Option<NodeID> {
+        self.tab_rects()
+            .iter()
+            .position(|rect| rect.contains(x, y))
+            .map(|i| self.tabs.windows[i])
+    }
+
+    pub fn draw(&self) {
+        let (ascent, descent) = self
+            .font
+            .map_or((0, 0), |font| self.display.font_extents(font));
+
+        for (i, rect) in self.tab_rects().iter().enumerate() {
+            let win = self.tabs.windows[i];
+            let background = if win == self.tabs.active {
+                self.style.focused_border_color
+            } else {
+                self.style.border_color
+            };
+            self.display.set_foreground(self.gc, background);
+            self.display.fill_rectangle(self.id, self.gc, rect);
+
+            let font = match self.font {
+                Some(font) => font,
+                None => continue,
+            };
+            let title = self.fit(font, &self.titles[i], rect.width as i32 - 2 * PADDING);
+            let baseline = rect.y + (rect.height as i32 + ascent - descent) / 2;
+            self.display.set_foreground(self.gc, config::TAB_TEXT_COLOR);
+            self.display
+                .draw_string(self.id, self.gc, rect.x + PADDING, baseline, &title);
+        }
+    }
+
+    pub fn show(&self) {
+        self.display.map_window(self.id);
+    }
+
+    pub fn hide(&self) {
+        self.display.unmap_window(self.id);
+    }
+
+    fn tab_rects(&self) -> Vec<Rect> {
+        let area = Rect::new(0, 0, self.tabs.rect.width, self.tabs.rect.height);
+        match self.tabs.mode {
+            Mode::Stacked => layout::rows(&area, self.tabs.windows.len()),
+            _ => layout::columns(&area, self.tabs.windows.len()),
+        }
+    }
+
+    // Longest start of the title that fits in `width` pixels
+    fn fit(&self, font: x::Font, title: &str, width: i32) -> String {
+        let mut title = title.to_string();
+        while !title.is_empty() && self.display.text_width(font, &title) > width {
+            title.pop();
+        }
+        title
+    }
+}
+
+impl Drop for TabStrip {
+    fn drop(&mut self) {
+        if let Some(font) = self.font {
+            self.display.free_font(font);
+        }
+        self.display.free_gc(self.gc);
+        self.display.destroy_window(self.id);
+    }
+}
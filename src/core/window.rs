@@ -1,39 +1,201 @@
+use std::cell::Cell;
 use std::rc::Rc;
 use x11::xlib;
 
-use crate::core::{config, node, x};
+use crate::core::{atom, config, node, x};
 
 pub type WindowID = u64;
 pub type WindowAttributes = xlib::XWindowAttributes;
 pub type WindowChanges = xlib::XWindowChanges;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Position {
-    x: i32,
-    y: i32,
+    pub x: i32,
+    pub y: i32,
 }
 
+// Outer size of the frame, borders included
+#[derive(Debug, Clone, Copy)]
 pub struct Size {
-    width: u32,
-    height: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+}
+
+// Size constraints from WM_NORMAL_HINTS, for the client without its border. Zero means unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SizeHints {
+    pub base_width: i32,
+    pub base_height: i32,
+    pub min_width: i32,
+    pub min_height: i32,
+    pub max_width: i32,
+    pub max_height: i32,
+    pub width_inc: i32,
+    pub height_inc: i32,
+    // Largest height / width ratio from the minimum aspect, largest width / height ratio from
+    // the maximum one
+    pub min_aspect: f32,
+    pub max_aspect: f32,
+}
+
+impl SizeHints {
+    // The base size stands in for a missing minimum size and the other way around
+    pub fn from_x(hints: &x::SizeHints) -> Self {
+        let flags = hints.flags;
+        let base = if flags & xlib::PBaseSize != 0 {
+            (hints.base_width, hints.base_height)
+        } else if flags & xlib::PMinSize != 0 {
+            (hints.min_width, hints.min_height)
+        } else {
+            (0, 0)
+        };
+        let min = if flags & xlib::PMinSize != 0 {
+            (hints.min_width, hints.min_height)
+        } else {
+            base
+        };
+        let max = if flags & xlib::PMaxSize != 0 {
+            (hints.max_width, hints.max_height)
+        } else {
+            (0, 0)
+        };
+        let inc = if flags & xlib::PResizeInc != 0 {
+            (hints.width_inc, hints.height_inc)
+        } else {
+            (0, 0)
+        };
+        let ratio = |a: i32, b: i32| if b > 0 { a as f32 / b as f32 } else { 0.0 };
+        let (min_aspect, max_aspect) = if flags & xlib::PAspect != 0 {
+            (
+                ratio(hints.min_aspect.y, hints.min_aspect.x),
+                ratio(hints.max_aspect.x, hints.max_aspect.y),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+
+        SizeHints {
+            base_width: base.0,
+            base_height: base.1,
+            min_width: min.0,
+            min_height: min.1,
+            max_width: max.0,
+            max_height: max.1,
+            width_inc: inc.0.max(0),
+            height_inc: inc.1.max(0),
+            min_aspect,
+            max_aspect,
+        }
+    }
+
+    // Closest client size the hints allow, following the ICCCM: aspect ratio without the
+    // base size, then increments above the base size, then minimum and maximum sizes
+    pub fn apply(&self, width: u32, height: u32) -> (u32, u32) {
+        let (mut width, mut height) = (width as i32, height as i32);
+        let base_is_min = self.base_width == self.min_width && self.base_height == self.min_height;
+
+        if !base_is_min {
+            width -= self.base_width;
+            height -= self.base_height;
+        }
+        if self.min_aspect > 0.0 && self.max_aspect > 0.0 && width > 0 && height > 0 {
+            if self.max_aspect < width as f32 / height as f32 {
+                width = (height as f32 * self.max_aspect + 0.5) as i32;
+            } else if self.min_aspect < height as f32 / width as f32 {
+                height = (width as f32 * self.min_aspect + 0.5) as i32;
+            }
+        }
+        if base_is_min {
+            width -= self.base_width;
+            height -= self.base_height;
+        }
+
+        if self.width_inc > 0 {
+            width -= width.rem_euclid(self.width_inc);
+        }
+        if self.height_inc > 0 {
+            height -= height.rem_euclid(self.height_inc);
+        }
+
+        width = (width + self.base_width).max(self.min_width);
+        height = (height + self.base_height).max(self.min_height);
+        if self.max_width > 0 {
+            width = width.min(self.max_width);
+        }
+        if self.max_height > 0 {
+            height = height.min(self.max_height);
+        }
+
+        (width.max(1) as u32, height.max(1) as u32)
+    }
 }
 
 pub struct Window {
     // Open display
     display: Rc<x::Display>,
+    atoms: atom::Atoms,
 
     id: WindowID,
     position: Position,
     size: Size,
 
+    style: config::Style,
+    borderless: bool,
     focused: bool,
-    marked: bool,
+    floating: bool,
+    // Where it goes when it floats again
+    floating_rect: Rect,
+    state: node::State,
+    // Where it was before going fullscreen
+    windowed_rect: Option<Rect>,
+    hints: SizeHints,
+    transient_for: Option<WindowID>,
+
+    // Hidden windows have their client unmapped too, rwm must not take the UnmapNotify
+    // events this causes for the client withdrawing
+    mapped: Cell<bool>,
+    expected_unmaps: Cell<usize>,
+    // Last WM_STATE set on the client
+    wm_state: Cell<Option<i32>>,
+    destroyed: bool,
 
     frame: WindowID,
 }
 
-#[allow(dead_code)]
 impl Window {
-    pub fn new(display: &Rc<x::Display>, id: WindowID, attrs: WindowAttributes) -> Window {
+    pub fn new(
+        display: &Rc<x::Display>,
+        atoms: &atom::Atoms,
+        id: WindowID,
+        attrs: WindowAttributes,
+        style: config::Style,
+    ) -> Window {
         // Create frame
         let frame = display.create_simple_window(
             display.root(),
@@ -41,49 +203,83 @@ impl Window {
             attrs.y,
             attrs.width as u32,
             attrs.height as u32,
-            config::BORDER_WIDTH,
-            config::BORDER_COLOR,
-            config::BACKGROUND,
+            style.border_width,
+            style.border_color,
+            style.background,
         );
         display.select_input(frame);
+        display.select_events(id, xlib::FocusChangeMask | xlib::PropertyChangeMask);
+        // The frame draws the border
+        display.set_window_border_width(id, 0);
         display.add_to_save_set(id);
         display.reparent_window(id, frame, 0, 0);
         display.map_window(frame);
 
+        let position = Position {
+            x: attrs.x,
+            y: attrs.y,
+        };
+        let size = Size {
+            width: attrs.width as u32 + 2 * style.border_width,
+            height: attrs.height as u32 + 2 * style.border_width,
+        };
+
         Window {
             display: Rc::clone(display),
+            atoms: *atoms,
             id,
-            position: Position {
-                x: attrs.y,
-                y: attrs.y,
-            },
-            size: Size {
-                width: attrs.width as u32,
-                height: attrs.height as u32,
-            },
+            position,
+            size,
+            style,
+            borderless: false,
             focused: false,
-            marked: false,
+            floating: false,
+            floating_rect: Rect::new(position.x, position.y, size.width, size.height),
+            state: node::State::default(),
+            windowed_rect: None,
+            hints: display
+                .get_normal_hints(id)
+                .map_or_else(SizeHints::default, |hints| SizeHints::from_x(&hints)),
+            transient_for: display.get_transient_for(id).filter(|parent| *parent != id),
+            mapped: Cell::new(attrs.map_state == x::IS_VIEWABLE),
+            expected_unmaps: Cell::new(0),
+            wm_state: Cell::new(None),
+            destroyed: false,
             frame,
         }
     }
 
-    pub fn position(&self) -> &Position {
-        &self.position
-    }
-
-    pub fn size(&self) -> &Size {
-        &self.size
+    fn border_color(&self) -> u64 {
+        if self.state.urgent && !self.focused {
+            self.style.urgent_border_color
+        } else if self.focused {
+            self.style.focused_border_color
+        } else {
+            self.style.border_color
+        }
     }
 
-    pub fn frame(&self) -> WindowID {
-        self.frame
+    // ICCCM state of the client: normal when shown, iconic when hidden and withdrawn once
+    // rwm lets it go
+    fn set_wm_state(&self, state: i32) {
+        if self.wm_state.replace(Some(state)) != Some(state) {
+            self.display.set_property(
+                self.id,
+                self.atoms.wm_state,
+                self.atoms.wm_state,
+                &[state as u64, 0],
+            );
+        }
     }
 
     pub fn unframe(&self) {
-        self.display.unmap_window(self.frame);
-        self.display
-            .reparent_window(self.id, self.display.root(), 0, 0);
-        self.display.remove_from_save_set(self.id);
+        if !self.destroyed {
+            self.set_wm_state(x::WITHDRAWN_STATE);
+            self.display.unmap_window(self.frame);
+            self.display
+                .reparent_window(self.id, self.display.root(), 0, 0);
+            self.display.remove_from_save_set(self.id);
+        }
         self.display.destroy_window(self.frame);
     }
 }
@@ -97,51 +293,200 @@ impl node::Node for Window {
         self.id() == id
     }
 
+    fn frame(&self) -> WindowID {
+        self.frame
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    // Moving the frame doesn't move the client relative to it, it has to be told
     fn set_position(&mut self, x: i32, y: i32) {
         self.position = Position { x, y };
         self.display.move_window(self.frame, x, y);
+        self.configure_notify();
     }
 
+    // Floating windows always get a size their hints allow
     fn set_size(&mut self, width: u32, height: u32) {
-        let width = width - 2 * config::BORDER_WIDTH;
-        let height = height - 2 * config::BORDER_WIDTH;
-
+        let (width, height) = if self.floating && !self.state.fullscreen {
+            self.constrain(width, height)
+        } else {
+            (width, height)
+        };
         self.size = Size { width, height };
 
+        // X sizes exclude the border
+        let width = width.saturating_sub(2 * self.border_width()).max(1);
+        let height = height.saturating_sub(2 * self.border_width()).max(1);
+
         self.display.resize_window(self.frame, width, height);
         self.display.resize_window(self.id, width, height);
+        self.configure_notify();
     }
 
-    fn focus(&mut self) {
-        self.focused = true;
+    fn constrain(&self, width: u32, height: u32) -> (u32, u32) {
+        let border = 2 * self.border_width();
+        let (width, height) = self
+            .hints
+            .apply(width.saturating_sub(border), height.saturating_sub(border));
+        (width + border, height + border)
+    }
+
+    fn configure_notify(&self) {
+        let border = self.border_width();
+        let rect = Rect::new(
+            self.position.x + border as i32,
+            self.position.y + border as i32,
+            self.size.width.saturating_sub(2 * border).max(1),
+            self.size.height.saturating_sub(2 * border).max(1),
+        );
+        self.display.send_configure_notify(self.id, &rect, 0);
+    }
+
+    fn update_size_hints(&mut self) {
+        self.hints = self
+            .display
+            .get_normal_hints(self.id)
+            .map_or_else(SizeHints::default, |hints| SizeHints::from_x(&hints));
+    }
+
+    fn transient_for(&self) -> Option<node::NodeID> {
+        self.transient_for
+    }
+
+    fn is_floating(&self) -> bool {
+        self.floating
+    }
+
+    // Floating windows get back their own position and size, the one requested when mapped
+    // or the last one they had while floating
+    fn set_floating(&mut self, floating: bool) {
+        if floating == self.floating {
+            return;
+        }
+
+        self.floating = floating;
+
+        if floating {
+            let rect = self.floating_rect;
+            self.set_position(rect.x, rect.y);
+            self.set_size(rect.width, rect.height);
+        } else {
+            let (position, size) = (self.position, self.size);
+            self.floating_rect = Rect::new(position.x, position.y, size.width, size.height);
+        }
+    }
+
+    fn state(&self) -> node::State {
+        self.state
+    }
+
+    // Floating windows get their place back when they leave fullscreen
+    fn set_state(&mut self, state: node::State) {
+        if state.fullscreen && !self.state.fullscreen {
+            let (position, size) = (self.position, self.size);
+            self.windowed_rect = Some(Rect::new(position.x, position.y, size.width, size.height));
+        }
+        if !state.fullscreen && self.state.fullscreen {
+            if let Some(rect) = self.windowed_rect.take().filter(|_| self.floating) {
+                self.set_position(rect.x, rect.y);
+                self.set_size(rect.width, rect.height);
+            }
+        }
+
+        self.state = state;
         self.display
-            .set_window_border(self.frame, config::FOCUSED_BORDER_COLOR);
+            .set_window_border(self.frame, self.border_color());
     }
 
-    fn unfocus(&mut self) {
-        self.focused = false;
+    fn set_style(&mut self, style: config::Style) {
+        self.style = style;
+
+        self.display
+            .set_window_border_width(self.frame, self.border_width());
         self.display
-            .set_window_border(self.frame, config::BORDER_COLOR);
+            .set_window_background(self.frame, style.background);
+        self.display
+            .set_window_border(self.frame, self.border_color());
+
+        // Same outer size, the client gets whatever the new border leaves
+        let size = self.size;
+        self.set_size(size.width, size.height);
     }
 
-    fn mark(&mut self) {
-        self.marked = true;
+    fn set_borderless(&mut self, borderless: bool) {
+        if borderless == self.borderless {
+            return;
+        }
+
+        self.borderless = borderless;
         self.display
-            .set_window_border(self.frame, config::MARKED_BORDER_COLOR);
+            .set_window_border_width(self.frame, self.border_width());
+        let size = self.size;
+        self.set_size(size.width, size.height);
     }
 
-    fn unmark(&mut self) {
-        self.marked = false;
+    fn border_width(&self) -> u32 {
+        if self.borderless {
+            0
+        } else {
+            self.style.border_width
+        }
+    }
+
+    // Seeing the window is all the attention it asked for
+    fn focus(&mut self) {
+        self.focused = true;
+        self.state.urgent = false;
         self.display
-            .set_window_border(self.frame, config::BORDER_COLOR);
+            .set_window_border(self.frame, self.style.focused_border_color);
+    }
+
+    fn unfocus(&mut self) {
+        self.focused = false;
+        self.display
+            .set_window_border(self.frame, self.border_color());
     }
 
     fn map(&self) {
+        self.mapped.set(true);
+        self.set_wm_state(x::NORMAL_STATE);
         self.display.map_window(self.id);
     }
 
-    fn reparent(&self, parent: WindowID) {
-        self.display.reparent_window(self.id, parent, 0, 0);
+    fn show(&self) {
+        if !self.mapped.get() {
+            self.map();
+        }
+        self.set_wm_state(x::NORMAL_STATE);
+        self.display.map_window(self.frame);
+    }
+
+    fn hide(&self) {
+        self.set_wm_state(x::ICONIC_STATE);
+        self.display.unmap_window(self.frame);
+        if self.mapped.replace(false) {
+            self.expected_unmaps.set(self.expected_unmaps.get() + 1);
+            self.display.unmap_window(self.id);
+        }
+    }
+
+    fn set_destroyed(&mut self) {
+        self.destroyed = true;
+    }
+
+    fn unmap_expected(&self) -> bool {
+        let expected = self.expected_unmaps.get();
+        if expected > 0 {
+            self.expected_unmaps.set(expected - 1);
+        }
+        expected > 0
     }
 }
 
@@ -150,3 +495,122 @@ impl Drop for Window {
         self.unframe();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn x_hints(flags: i64) -> x::SizeHints {
+        let mut hints: x::SizeHints = unsafe { std::mem::zeroed() };
+        hints.flags = flags;
+        hints
+    }
+
+    #[test]
+    fn unset_hints_keep_any_size() {
+        let hints = SizeHints::from_x(&x_hints(0));
+        assert_eq!(hints, SizeHints::default());
+        assert_eq!(hints.apply(800, 600), (800, 600));
+        assert_eq!(hints.apply(1, 1), (1, 1));
+    }
+
+    #[test]
+    fn base_and_min_stand_in_for_each_other() {
+        let mut x = x_hints(xlib::PMinSize);
+        x.min_width = 100;
+        x.min_height = 50;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!((hints.base_width, hints.base_height), (100, 50));
+
+        let mut x = x_hints(xlib::PBaseSize);
+        x.base_width = 20;
+        x.base_height = 30;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!((hints.min_width, hints.min_height), (20, 30));
+        assert_eq!(hints.apply(5, 5), (20, 30));
+    }
+
+    #[test]
+    fn increments_count_from_the_base_size() {
+        let mut x = x_hints(xlib::PBaseSize | xlib::PMinSize | xlib::PResizeInc);
+        x.base_width = 4;
+        x.base_height = 4;
+        x.min_width = 10;
+        x.min_height = 17;
+        x.width_inc = 6;
+        x.height_inc = 13;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!(hints.apply(800, 600), (796, 589));
+        assert_eq!(hints.apply(12, 12), (10, 17));
+
+        // Without a base size the minimum one is where increments start
+        let mut x = x_hints(xlib::PMinSize | xlib::PResizeInc);
+        x.min_width = 100;
+        x.min_height = 50;
+        x.width_inc = 10;
+        x.height_inc = 10;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!(hints.apply(255, 147), (250, 140));
+    }
+
+    #[test]
+    fn aspect_limits() {
+        let mut x = x_hints(xlib::PAspect);
+        x.min_aspect.x = 16;
+        x.min_aspect.y = 9;
+        x.max_aspect.x = 16;
+        x.max_aspect.y = 9;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!(hints.apply(1600, 900), (1600, 900));
+        assert_eq!(hints.apply(1920, 500), (889, 500));
+        assert_eq!(hints.apply(500, 1000), (500, 281));
+
+        // A range of ratios leaves sizes within it alone
+        let mut x = x_hints(xlib::PAspect);
+        x.min_aspect.x = 1;
+        x.min_aspect.y = 2;
+        x.max_aspect.x = 2;
+        x.max_aspect.y = 1;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!(hints.apply(300, 200), (300, 200));
+        assert_eq!(hints.apply(900, 100), (200, 100));
+    }
+
+    #[test]
+    fn aspect_ignores_a_base_size_distinct_from_the_minimum() {
+        let mut x = x_hints(xlib::PBaseSize | xlib::PMinSize | xlib::PAspect);
+        x.base_width = 10;
+        x.base_height = 10;
+        x.min_width = 20;
+        x.min_height = 20;
+        x.min_aspect.x = 1;
+        x.min_aspect.y = 1;
+        x.max_aspect.x = 1;
+        x.max_aspect.y = 1;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!(hints.apply(110, 60), (60, 60));
+    }
+
+    #[test]
+    fn min_and_max_clamps() {
+        let mut x = x_hints(xlib::PMinSize | xlib::PMaxSize);
+        x.min_width = 200;
+        x.min_height = 100;
+        x.max_width = 640;
+        x.max_height = 480;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!(hints.apply(50, 50), (200, 100));
+        assert_eq!(hints.apply(1000, 1000), (640, 480));
+        assert_eq!(hints.apply(400, 300), (400, 300));
+
+        // Fixed size windows
+        let mut x = x_hints(xlib::PMinSize | xlib::PMaxSize);
+        x.min_width = 300;
+        x.min_height = 300;
+        x.max_width = 300;
+        x.max_height = 300;
+        let hints = SizeHints::from_x(&x);
+        assert_eq!(hints.apply(800, 600), (300, 300));
+        assert_eq!(hints.apply(10, 10), (300, 300));
+    }
+}
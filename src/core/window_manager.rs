@@ -1,136 +1,1836 @@
+use std::collections::HashMap;
+use std::os::unix::process::CommandExt;
+use std::process;
 use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Instant;
+use x11::xlib;
 
 use crate::core::{
+    action::Action,
+    atom, config, container, cursor, dock,
     event::{self, Event},
-    layout, node,
+    ewmh, key, layout,
+    monitor::Monitor,
+    mouse, node,
     node::Node,
-    window, x,
+    server,
+    tabs::TabStrip,
+    window,
+    workspace::Workspace,
+    x,
 };
 
+// How long the event loop blocks on X before checking other sources
+const POLL_TIMEOUT: i32 = 50;
+
+// _NET_WM_DESKTOP of windows shown on every workspace
+const ALL_DESKTOPS: u64 = 0xFFFF_FFFF;
+
+// Actions of _NET_WM_STATE requests
+const STATE_REMOVE: i64 = 0;
+const STATE_ADD: i64 = 1;
+const STATE_TOGGLE: i64 = 2;
+
 pub struct WindowManager {
     display: Rc<x::Display>,
+    atoms: atom::Atoms,
+    ewmh: ewmh::Ewmh,
+    config: config::Config,
+
+    workspaces: Vec<Workspace>,
 
-    windows: Vec<Box<dyn node::Node>>,
+    monitors: Vec<Monitor>,
+    focused_monitor: usize,
+    docks: Vec<dock::Dock>,
 
     layouts: Vec<Box<dyn layout::Layout>>,
-    selected_layout: usize,
+
+    keys: key::KeyBindings,
+    buttons: mouse::ButtonBindings,
+    drag: Option<mouse::Drag>,
+    // Pointer shapes while dragging, created once
+    move_cursor: cursor::CursorID,
+    resize_cursor: cursor::CursorID,
+    // Window last given the keyboard focus, None for the root window
+    input_focus: Option<node::NodeID>,
+
+    // Windows asked to close and when their ping times out
+    pings: HashMap<node::NodeID, Instant>,
+    // Windows that didn't answer, closing them again kills their process
+    unresponsive: Vec<node::NodeID>,
 }
 
 impl WindowManager {
-    pub fn new(display: x::Display) -> WindowManager {
-        WindowManager {
-            display: Rc::new(display),
+    pub fn new(display: x::Display, config: config::Config) -> WindowManager {
+        let atoms = atom::Atoms::new(&display);
+        let display = Rc::new(display);
+        let move_cursor = display.create_font_cursor(cursor::MOVE);
+        let resize_cursor = display.create_font_cursor(cursor::RESIZE);
 
-            windows: Vec::new(),
+        let mut wm = WindowManager {
+            ewmh: ewmh::Ewmh::new(&display, atoms),
+            atoms,
+            display,
+            config: config::Config::default(),
 
-            layouts: vec![
-                Box::new(layout::ColumnLayout(800, 600)),
-                // Box::new(layout::RowLayout(800, 600)),
-            ],
-            selected_layout: 0,
-        }
+            workspaces: Vec::new(),
+
+            monitors: Vec::new(),
+            focused_monitor: 0,
+            docks: Vec::new(),
+
+            layouts: Vec::new(),
+
+            keys: key::KeyBindings::new(Vec::new()),
+            buttons: mouse::ButtonBindings::new(Vec::new()),
+            drag: None,
+            move_cursor,
+            resize_cursor,
+            input_focus: None,
+
+            pings: HashMap::new(),
+            unresponsive: Vec::new(),
+        };
+        wm.set_config(config);
+        wm.set_monitors(wm.display.monitors());
+
+        wm
     }
 
     pub fn scan(&mut self) -> Result<usize, String> {
         let (_, _, window_ids) = self.display.query_tree(self.display.root())?;
         let len = window_ids.len();
 
-        let mut focused_window = false;
         for win_id in window_ids {
             let attrs = self.display.get_window_attributes(win_id)?;
 
             if attrs.override_redirect > 0 || attrs.map_state != x::IS_VIEWABLE {
                 continue;
             }
-
-            let mut win = window::Window::new(&self.display, win_id, attrs);
-            if !focused_window {
-                win.focus();
-                focused_window = true;
+            if self.map_unmanaged(win_id) {
+                continue;
             }
 
-            self.windows.push(Box::new(win));
+            // Existing windows stay on the monitor they are on
+            let m = self
+                .monitor_at(attrs.x + attrs.width / 2, attrs.y + attrs.height / 2)
+                .unwrap_or(self.focused_monitor);
+
+            let mut win =
+                window::Window::new(&self.display, &self.atoms, win_id, attrs, self.config.style);
+            if self.should_float(win_id) || win.transient_for().is_some() {
+                win.set_floating(true);
+            }
+            win.set_state(self.initial_state(win_id));
+            self.workspaces[self.monitors[m].workspace].add(Box::new(win));
         }
 
-        self.apply_selected_layout();
+        for monitor in self.monitors.iter() {
+            self.workspaces[monitor.workspace].focus(Some(0));
+        }
+        self.arrange_all();
 
         Ok(len)
     }
 
-    pub fn grab_events(&self) {}
+    pub fn grab_events(&mut self) {
+        let root = self.display.root();
+        self.keys.grab(&self.display, root);
+        self.buttons
+            .grab(&self.display, root, self.keys.numlock_mask());
+    }
 
-    pub fn run(&mut self) -> Result<(), String> {
+    pub fn run(&mut self, requests: mpsc::Receiver<server::Request>) -> Result<(), String> {
         self.grab_events();
+        self.display.select_screen_changes();
+        self.update_focus();
+        self.update_ewmh();
 
         loop {
-            match self.display.next_event() {
-                Event::ConfigureRequest(configure_req) => self.on_configure_request(configure_req),
-                Event::MapRequest(req) => self.on_map_request(req),
-                Event::UnmapNotify(unmap_req) => self.on_unmap_notify(unmap_req),
-                _ => (),
+            let mut changed = false;
+            while let Ok(request) = requests.try_recv() {
+                let reply = self.handle(request.action.clone());
+                request.respond(reply);
+                changed = true;
+            }
+
+            if let Some(event) = self.display.wait_event(POLL_TIMEOUT) {
+                // Drags and redraws don't change anything pagers care about
+                changed |= !matches!(event, Event::MotionNotify(..) | Event::Expose(_));
+                self.dispatch(event);
+            }
+
+            self.check_pings();
+
+            if changed {
+                self.update_focus();
+                self.update_ewmh();
             }
         }
     }
 
-    fn apply_selected_layout(&mut self) {
-        self.layouts[self.selected_layout].apply(&mut self.windows.iter_mut());
+    fn dispatch(&mut self, event: Event) {
+        match event {
+            Event::ConfigureRequest(configure_req) => self.on_configure_request(configure_req),
+            Event::MapRequest(req) => self.on_map_request(req),
+            Event::ClientMessage(message) => self.on_client_message(message),
+            Event::UnmapNotify(unmap_req) => self.on_unmap_notify(unmap_req),
+            Event::DestroyNotify(destroy_event) => self.on_destroy_notify(destroy_event),
+            Event::KeyPress(key_event) => self.on_key_press(key_event),
+            Event::MappingNotify(mapping_event) => self.on_mapping_notify(mapping_event),
+            Event::ButtonPress(button_event) => self.on_button_press(button_event),
+            Event::ButtonRelease(button_event) => self.on_button_release(button_event),
+            Event::MotionNotify(motion_event) => self.on_motion_notify(motion_event),
+            Event::Expose(expose_event) => self.on_expose(expose_event),
+            Event::PropertyNotify(property_event) => self.on_property_notify(property_event),
+            Event::FocusIn(focus_event) => self.on_focus_in(focus_event),
+            Event::ScreenChange => self.on_screen_change(),
+            Event::ConfigureNotify(configure_event)
+                if configure_event.window == self.display.root() =>
+            {
+                self.on_screen_change()
+            }
+            _ => (),
+        }
     }
 
-    fn on_configure_request(&mut self, req: event::ConfigureRequestEvent) {
-        let mut changes = window::WindowChanges {
-            x: req.x,
-            y: req.y,
-            width: req.width,
-            height: req.height,
-            border_width: req.border_width,
-            sibling: req.above,
-            stack_mode: req.detail,
+    // Gives the keyboard focus to the focused window of the current workspace, the ICCCM way:
+    // clients that take input get it directly, those listing WM_TAKE_FOCUS are asked to take
+    // it themselves, and the root window gets it when there is no one to give it to
+    fn update_focus(&mut self) {
+        let target = self.workspaces[self.current()]
+            .focused()
+            .filter(|win| !win.state().hidden)
+            .map(|win| win.id());
+        if target == self.input_focus {
+            return;
+        }
+        self.input_focus = target;
+
+        let id = match target {
+            Some(id) => id,
+            None => {
+                self.display.set_input_focus(self.display.root());
+                return;
+            }
+        };
+
+        // Clients without WM_HINTS take input
+        let input = self
+            .display
+            .get_wm_hints(id)
+            .is_none_or(|hints| hints.flags & xlib::InputHint == 0 || hints.input != 0);
+        let take_focus = self
+            .display
+            .get_wm_protocols(id)
+            .contains(&self.atoms.wm_take_focus);
+
+        if input {
+            self.display.set_input_focus(id);
+        } else if !take_focus {
+            self.display.set_input_focus(self.display.root());
+        }
+        if take_focus {
+            self.send_protocol(id, self.atoms.wm_take_focus);
+        }
+    }
+
+    // Publishes the current state on the root window for pagers and bars
+    fn update_ewmh(&mut self) {
+        let windows = || self.workspaces.iter().flat_map(|ws| ws.windows().iter());
+
+        // Bottom to top, windows restack() left out go below the others
+        let stacking = self
+            .workspaces
+            .iter()
+            .flat_map(|ws| {
+                let left_out = ws
+                    .windows()
+                    .iter()
+                    .map(|win| win.id())
+                    .filter(move |id| !ws.stacking.contains(id));
+                let stacked = ws
+                    .stacking
+                    .iter()
+                    .rev()
+                    .copied()
+                    .filter(move |id| ws.contains(*id));
+                left_out.chain(stacked)
+            })
+            .collect();
+
+        let screen = self.screen();
+        let state = ewmh::State {
+            clients: windows().map(|win| win.id()).collect(),
+            stacking,
+            active: self.workspaces[self.current()]
+                .focused()
+                .map(|win| win.id()),
+            desktops: self
+                .workspaces
+                .iter()
+                .map(|ws| ws.name().to_string())
+                .collect(),
+            current_desktop: self.current(),
+            workarea: self
+                .workspaces
+                .iter()
+                .map(|ws| ws.area.unwrap_or(screen))
+                .collect(),
+            client_desktops: self
+                .workspaces
+                .iter()
+                .enumerate()
+                .flat_map(|(i, ws)| {
+                    ws.windows().iter().map(move |win| {
+                        let desktop = if win.state().sticky {
+                            ALL_DESKTOPS
+                        } else {
+                            i as u64
+                        };
+                        (win.id(), desktop)
+                    })
+                })
+                .collect(),
+            client_states: windows()
+                .map(|win| (win.id(), self.atoms.state_atoms(&win.state())))
+                .collect(),
+        };
+
+        self.ewmh.update(state);
+    }
+
+    // Smallest rectangle around every monitor
+    fn screen(&self) -> window::Rect {
+        let left = self.monitors.iter().map(|m| m.rect.x).min().unwrap_or(0);
+        let top = self.monitors.iter().map(|m| m.rect.y).min().unwrap_or(0);
+        let right = self
+            .monitors
+            .iter()
+            .map(|m| m.rect.x + m.rect.width as i32)
+            .max()
+            .unwrap_or(0);
+        let bottom = self
+            .monitors
+            .iter()
+            .map(|m| m.rect.y + m.rect.height as i32)
+            .max()
+            .unwrap_or(0);
+
+        window::Rect::new(left, top, (right - left) as u32, (bottom - top) as u32)
+    }
+
+    pub fn handle(&mut self, action: Action) -> server::Reply {
+        match action {
+            Action::FocusNext => self.focus_next(),
+            Action::FocusPrev => self.focus_prev(),
+            Action::Close => self.close_focused(),
+            Action::Kill => self.kill_focused(),
+            Action::Spawn(command) => self.spawn(&command),
+            Action::NextLayout => self.next_layout(),
+            Action::PrevLayout => self.prev_layout(),
+            Action::Reload => return self.reload().map(|_| String::new()),
+            Action::Status => return Ok(self.status()),
+            Action::Workspace(i) => self.switch_workspace(i),
+            Action::MoveToWorkspace(i, follow) => self.move_to_workspace(i, follow),
+            Action::FocusNextMonitor => self.focus_monitor(self.next_monitor()),
+            Action::FocusPrevMonitor => self.focus_monitor(self.prev_monitor()),
+            Action::MoveToNextMonitor => self.move_to_monitor(self.next_monitor()),
+            Action::MoveToPrevMonitor => self.move_to_monitor(self.prev_monitor()),
+            Action::GrowMaster => self.resize_master(config::MASTER_FACTOR_STEP),
+            Action::ShrinkMaster => self.resize_master(-config::MASTER_FACTOR_STEP),
+            Action::IncMaster => self.add_master(1),
+            Action::DecMaster => self.add_master(-1),
+            Action::Zoom => self.zoom(),
+            Action::ToggleFloating => self.toggle_floating(),
+            Action::IncGaps => self.resize_gaps(config::GAP_STEP, 0),
+            Action::DecGaps => self.resize_gaps(-config::GAP_STEP, 0),
+            Action::IncOuterGaps => self.resize_gaps(0, config::GAP_STEP),
+            Action::DecOuterGaps => self.resize_gaps(0, -config::GAP_STEP),
+            Action::SetGaps(inner, outer) => self.set_gaps(inner, outer),
+            Action::ToggleSmartGaps => self.toggle_smart_gaps(),
+            Action::Preselect(direction) => self.preselect(direction),
+            Action::GrowSplit => self.resize_split(config::SPLIT_RATIO_STEP),
+            Action::ShrinkSplit => self.resize_split(-config::SPLIT_RATIO_STEP),
+            Action::Rotate => self.update_tree(|tree, id| tree.rotate(id)),
+            Action::Flip(orientation) => self.update_tree(|tree, id| tree.flip(id, orientation)),
+            Action::ContainerMode(mode) => return self.set_container_mode(mode),
+        }
+
+        Ok(String::new())
+    }
+
+    // Keeps the current configuration if the file is broken
+    pub fn reload(&mut self) -> Result<(), String> {
+        let config = match config::Config::path() {
+            Some(path) => config::Config::from_file(&path)?.unwrap_or_default(),
+            None => config::Config::default(),
+        };
+
+        self.set_config(config);
+        self.grab_events();
+
+        for workspace in self.workspaces.iter_mut() {
+            for win in workspace.windows_mut() {
+                win.set_style(self.config.style);
+            }
+        }
+        self.arrange_all();
+
+        Ok(())
+    }
+
+    fn set_config(&mut self, mut config: config::Config) {
+        self.layouts = config
+            .layouts
+            .iter()
+            .filter_map(|name| layout::from_name(name))
+            .collect();
+        for workspace in self.workspaces.iter_mut() {
+            if workspace.selected_layout >= self.layouts.len() {
+                workspace.selected_layout = 0;
+            }
+        }
+        self.set_workspaces(&config);
+
+        self.keys = key::KeyBindings::new(config.keybindings.drain(..).collect());
+        self.buttons = mouse::ButtonBindings::new(config.mousebindings.drain(..).collect());
+        self.config = config;
+    }
+
+    // Workspaces are matched by position, windows on dropped ones end up in the last one left.
+    // Layout settings adjusted at runtime go back to the configured ones.
+    fn set_workspaces(&mut self, config: &config::Config) {
+        let names = &config.workspaces;
+        while self.workspaces.len() > names.len() {
+            let mut dropped = self.workspaces.pop().unwrap();
+            let last = self.workspaces.len() - 1;
+
+            for win in dropped.take_windows() {
+                self.workspaces[last].add(win);
+            }
+        }
+
+        for (i, name) in names.iter().enumerate() {
+            let params = &config.layout_params;
+            let gaps = config.gaps(name);
+            match self.workspaces.get_mut(i) {
+                Some(workspace) => {
+                    workspace.set_name(name);
+                    workspace.params.master_factor = params.master_factor;
+                    workspace.params.master_count = params.master_count;
+                    workspace.params.gaps = gaps;
+                }
+                None => {
+                    let mut params = params.clone();
+                    params.gaps = gaps;
+                    self.workspaces.push(Workspace::new(name, params));
+                }
+            }
+        }
+
+        // Monitors that had no workspace left may fit now
+        if !self.monitors.is_empty() {
+            self.set_monitors(self.display.monitors());
+        }
+    }
+
+    // Monitors keep their workspace when their area or position didn't change, new ones get
+    // the first one not shown. There can't be more monitors than workspaces.
+    fn set_monitors(&mut self, mut rects: Vec<window::Rect>) {
+        // There is always at least one monitor, the whole screen if nothing else is known
+        if rects.is_empty() {
+            rects.push(self.display.screen_rect());
+        }
+
+        let len = self.workspaces.len();
+        let focused = self.monitors.get(self.focused_monitor).map(|m| m.rect);
+        let mut monitors: Vec<Monitor> = Vec::new();
+
+        for (i, rect) in rects.into_iter().take(len).enumerate() {
+            let same_rect = self.monitors.iter().find(|m| m.rect == rect);
+            let same_index = self.monitors.get(i);
+
+            let workspace = same_rect
+                .into_iter()
+                .chain(same_index)
+                .map(|monitor| monitor.workspace)
+                .chain(0..len)
+                .find(|ws| *ws < len && monitors.iter().all(|m| m.workspace != *ws))
+                .unwrap_or(0);
+
+            monitors.push(Monitor::new(rect, workspace));
+        }
+
+        self.monitors = monitors;
+        self.focused_monitor = focused
+            .and_then(|rect| self.monitors.iter().position(|m| m.rect == rect))
+            .unwrap_or_else(|| self.focused_monitor.min(self.monitors.len() - 1));
+
+        // Monitors that kept their area kept their workspace, there are no sticky windows
+        // to carry over
+        self.update_visibility(&[]);
+    }
+
+    fn current(&self) -> usize {
+        self.monitors[self.focused_monitor].workspace
+    }
+
+    fn monitor_of(&self, workspace: usize) -> Option<usize> {
+        self.monitors
+            .iter()
+            .position(|monitor| monitor.workspace == workspace)
+    }
+
+    fn monitor_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.monitors
+            .iter()
+            .position(|monitor| monitor.rect.contains(x, y))
+    }
+
+    fn next_monitor(&self) -> usize {
+        (self.focused_monitor + 1) % self.monitors.len()
+    }
+
+    fn prev_monitor(&self) -> usize {
+        match self.focused_monitor {
+            0 => self.monitors.len() - 1,
+            i => i - 1,
+        }
+    }
+
+    // Show the workspaces on a monitor and hide everything else. `previous` holds the
+    // workspace each monitor showed before, sticky windows follow their monitor to the new
+    // one without taking its focus.
+    fn update_visibility(&mut self, previous: &[usize]) {
+        // Unmapped windows lose the X focus, it has to be given again
+        self.input_focus = None;
+
+        // Everything is taken out first so that two monitors can swap workspaces
+        let mut moved = Vec::new();
+        for (m, from) in previous.iter().copied().enumerate() {
+            let to = match self.monitors.get(m) {
+                Some(monitor) if monitor.workspace != from => monitor.workspace,
+                _ => continue,
+            };
+            let sticky: Vec<node::NodeID> = self.workspaces[from]
+                .windows()
+                .iter()
+                .filter(|win| win.state().sticky)
+                .map(|win| win.id())
+                .collect();
+            for id in sticky {
+                if let Some(win) = self.workspaces[from].remove(id) {
+                    moved.push((win, from, to));
+                }
+            }
+        }
+        for (mut win, from, to) in moved {
+            self.translate_floating(win.as_mut(), from, to);
+            self.workspaces[to].insert(win);
+        }
+
+        for (i, workspace) in self.workspaces.iter().enumerate() {
+            if self.monitor_of(i).is_some() {
+                workspace.show();
+            }
+        }
+        for (i, workspace) in self.workspaces.iter().enumerate() {
+            if self.monitor_of(i).is_none() {
+                workspace.hide();
+            }
+        }
+    }
+
+    // Part of the monitor left to windows once docks took their space
+    fn work_area(&self, m: usize) -> window::Rect {
+        let screen = self.screen();
+        self.docks.iter().fold(self.monitors[m].rect, |area, dock| {
+            dock.strut.apply(&area, &screen)
+        })
+    }
+
+    fn arrange(&mut self, i: usize) {
+        let (monitor, area) = match self.monitor_of(i) {
+            Some(m) => (self.monitors[m].rect, self.work_area(m)),
+            None => return,
         };
 
-        if let Some(_) = self.windows.iter_mut().find(|win| win.id() == req.window) {
-            // self.display
-            //     .configure_window(win.frame(), req.value_mask, &mut changes);
+        let workspace = &mut self.workspaces[i];
+        let layout = &self.layouts[workspace.selected_layout];
+
+        // Carry floating windows over when the workspace lands on another monitor, fullscreen
+        // ones cover it whole, docks included
+        let previous = workspace.area.replace(area);
+        for win in workspace.windows_mut() {
+            let state = win.state();
+            if state.hidden {
+                win.hide();
+                continue;
+            }
+            win.show();
+
+            if state.fullscreen {
+                win.set_borderless(true);
+                win.set_position(monitor.x, monitor.y);
+                win.set_size(monitor.width, monitor.height);
+            } else if win.is_floating() {
+                win.set_borderless(false);
+                keep_inside(win.as_mut(), previous, area);
+            }
         }
 
+        // Windows may have become floating or tiled since the tree was last updated
+        let tiled = workspace.tiled();
+        let focused = workspace
+            .focused()
+            .filter(|win| win.is_tiled())
+            .map(|win| win.id());
+        workspace.params.tree.sync(&tiled, focused, &area);
+
+        // Windows hidden behind others don't count for smart gaps
+        let gaps = workspace.params.gaps;
+        let visible = if layout.is_stacked() {
+            tiled.len().min(1)
+        } else {
+            let hidden: usize = layout
+                .tabs(&area, &workspace.params)
+                .iter()
+                .map(|tabs| tabs.windows.len() - 1)
+                .sum();
+            tiled.len() - hidden
+        };
+        let smart = gaps.smart && visible == 1;
+
+        let area = if smart {
+            area
+        } else {
+            layout::shrink(&area, gaps.outer, gaps.outer, gaps.outer, gaps.outer)
+        };
+        let mut rects = layout.arrange(&area, &tiled, &workspace.params);
+        if !smart {
+            rects = layout::inner_gaps(rects, &area, gaps.inner);
+        }
+
+        let windows = workspace
+            .windows_mut()
+            .iter_mut()
+            .filter(|win| win.is_tiled());
+        for (win, rect) in windows.zip(rects) {
+            win.set_borderless(smart);
+            let (width, height) = if self.config.size_hints_tiled {
+                win.constrain(rect.width, rect.height)
+            } else {
+                (rect.width, rect.height)
+            };
+            win.set_position(
+                rect.x + (rect.width as i32 - width as i32) / 2,
+                rect.y + (rect.height as i32 - height as i32) / 2,
+            );
+            win.set_size(width, height);
+        }
+
+        // Tab strips are reused in order, extra ones are destroyed
+        let groups = layout.tabs(&area, &workspace.params);
+        workspace.tabs.truncate(groups.len());
+        for (j, tabs) in groups.into_iter().enumerate() {
+            match workspace.tabs.get_mut(j) {
+                Some(strip) => strip.update(tabs, self.config.style),
+                None => workspace
+                    .tabs
+                    .push(TabStrip::new(&self.display, tabs, self.config.style)),
+            }
+        }
+
+        self.restack(i);
+    }
+
+    // From the top: fullscreen windows, windows kept above, floating windows, tiled windows and
+    // windows kept below. Stacked layouts and containers only show their focused window.
+    fn restack(&mut self, i: usize) {
+        let workspace = &self.workspaces[i];
+        let focused = workspace.focused().map(|win| win.id());
+
+        // Lower rank goes higher, the focused window first within its rank. Tiled windows
+        // other than the focused one and the active tabs may be covered.
+        let rank = |win: &dyn node::Node| {
+            let state = win.state();
+            let shown = win.is(focused.unwrap_or(0))
+                || workspace.tabs.iter().any(|strip| win.is(strip.active()));
+            match () {
+                _ if state.fullscreen => 0,
+                _ if state.above => 1,
+                _ if state.below => 5,
+                _ if win.is_floating() => 2,
+                _ if shown => 3,
+                _ => 4,
+            }
+        };
+
+        let parent_frame = |win: &dyn node::Node| {
+            let parent = win.transient_for()?;
+            workspace
+                .windows()
+                .iter()
+                .find(|other| other.is(parent))
+                .map(|other| other.frame())
+        };
+
+        let mut frames: Vec<(usize, bool, window::WindowID, Option<window::WindowID>)> = workspace
+            .windows()
+            .iter()
+            .filter(|win| !win.state().hidden)
+            .map(|win| {
+                let win = win.as_ref();
+                let unfocused = !win.is(focused.unwrap_or(0));
+                (rank(win), unfocused, win.frame(), parent_frame(win))
+            })
+            .collect();
+        // Tab strips go with the tiled windows they sit on
+        frames.extend(
+            workspace
+                .tabs
+                .iter()
+                .map(|strip| (3, false, strip.id(), None)),
+        );
+        frames.sort_by_key(|(rank, unfocused, _, _)| (*rank, *unfocused));
+
+        // Transients go right above their parent
+        for i in 0..frames.len() {
+            let parent = frames[i]
+                .3
+                .and_then(|parent| frames.iter().position(|(_, _, frame, _)| *frame == parent));
+            if let Some(j) = parent.filter(|j| *j < i) {
+                let transient = frames.remove(i);
+                frames.insert(j, transient);
+            }
+        }
+
+        let frames: Vec<window::WindowID> =
+            frames.into_iter().map(|(_, _, frame, _)| frame).collect();
+        if let Some(top) = frames.first() {
+            self.display.raise_window(*top);
+            self.display.restack_windows(&frames);
+        }
+
+        // Kept for _NET_CLIENT_LIST_STACKING, tab strips are not clients
+        let stacking = frames
+            .iter()
+            .filter_map(|frame| workspace.windows().iter().find(|win| win.frame() == *frame))
+            .map(|win| win.id())
+            .collect();
+        self.workspaces[i].stacking = stacking;
+    }
+
+    // Current workspace and layout, e.g. `2 monocle [3/7]` when windows are stacked
+    fn status(&self) -> String {
+        let workspace = &self.workspaces[self.current()];
+        let layout = &self.layouts[workspace.selected_layout];
+        let mut status = format!("{} {}", workspace.name(), layout.name());
+
+        if layout.is_stacked() {
+            let tiled: Vec<&Box<dyn node::Node>> = workspace
+                .windows()
+                .iter()
+                .filter(|win| win.is_tiled())
+                .collect();
+            let focused = workspace
+                .focused()
+                .and_then(|focused| tiled.iter().position(|win| win.is(focused.id())))
+                .map_or(0, |i| i + 1);
+            status.push_str(&format!(" [{}/{}]", focused, tiled.len()));
+        }
+
+        status
+    }
+
+    fn arrange_all(&mut self) {
+        for m in 0..self.monitors.len() {
+            self.arrange(self.monitors[m].workspace);
+        }
+    }
+
+    // Workspace the rules send the window to, if any
+    fn apply_rules(&self, win: &mut window::Window) -> Option<usize> {
+        let (instance, class) = self.display.get_class_hint(win.id()).unwrap_or_default();
+        let title = self.display.fetch_name(win.id()).unwrap_or_default();
+
+        let mut target = None;
+        for rule in self.config.rules.iter() {
+            if !rule.matches(&class, &instance, &title) {
+                continue;
+            }
+
+            if rule.floating {
+                win.set_floating(true);
+            }
+            if let Some(name) = rule.workspace.as_ref() {
+                target = self.workspaces.iter().position(|ws| ws.name() == name);
+            }
+        }
+
+        target
+    }
+
+    // Dialogs, splash screens and windows that cannot be resized
+    fn should_float(&self, id: node::NodeID) -> bool {
+        let types = self
+            .display
+            .get_property(id, self.atoms.net_wm_window_type, self.atoms.atom);
+        let floating = [
+            self.atoms.net_wm_window_type_dialog,
+            self.atoms.net_wm_window_type_splash,
+            self.atoms.net_wm_window_type_toolbar,
+            self.atoms.net_wm_window_type_utility,
+        ];
+        if types.iter().any(|kind| floating.contains(kind)) {
+            return true;
+        }
+
+        let fixed = xlib::PMinSize | xlib::PMaxSize;
+        self.display.get_normal_hints(id).is_some_and(|hints| {
+            hints.flags & fixed == fixed
+                && hints.min_width == hints.max_width
+                && hints.min_height == hints.max_height
+        })
+    }
+
+    // Only normal windows and dialogs are focused when they appear, splash screens, toolbars
+    // and utility windows are not
+    fn takes_focus(&self, id: node::NodeID) -> bool {
+        let types = self
+            .display
+            .get_property(id, self.atoms.net_wm_window_type, self.atoms.atom);
+        let focused = [
+            self.atoms.net_wm_window_type_normal,
+            self.atoms.net_wm_window_type_dialog,
+        ];
+        types.is_empty() || types.iter().any(|kind| focused.contains(kind))
+    }
+
+    // States set by the client before mapping the window
+    fn initial_state(&self, id: node::NodeID) -> node::State {
+        let mut state = node::State::default();
+        for atom in self
+            .display
+            .get_property(id, self.atoms.net_wm_state, self.atoms.atom)
+        {
+            if let Some(flag) = self.atoms.state_flag(&mut state, atom) {
+                *flag = true;
+            }
+        }
+        state
+    }
+
+    // Centers new floating windows on their monitor unless they asked for a position,
+    // transients are centered over their parent unless the user gave a position
+    fn place(&self, win: &mut window::Window, workspace: usize) {
+        let parent = win.transient_for().and_then(|id| {
+            self.workspaces[workspace]
+                .windows()
+                .iter()
+                .find(|w| w.is(id))
+        });
+        let positioned = match parent {
+            Some(_) => xlib::USPosition,
+            None => xlib::USPosition | xlib::PPosition,
+        };
+        if self
+            .display
+            .get_normal_hints(win.id())
+            .is_some_and(|hints| hints.flags & positioned != 0)
+        {
+            return;
+        }
+
+        let area = match parent {
+            Some(parent) => {
+                let (position, size) = (parent.position(), parent.size());
+                window::Rect::new(position.x, position.y, size.width, size.height)
+            }
+            None => {
+                let m = self.monitor_of(workspace).unwrap_or(self.focused_monitor);
+                self.work_area(m)
+            }
+        };
+        let size = win.size();
+        win.set_position(
+            area.x + (area.width as i32 - size.width as i32) / 2,
+            area.y + (area.height as i32 - size.height as i32) / 2,
+        );
+    }
+
+    fn toggle_floating(&mut self) {
+        let current = self.current();
+        if let Some(win) = self.workspaces[current].focused_mut() {
+            let floating = !win.is_floating();
+            win.set_floating(floating);
+        }
+        self.arrange(current);
+    }
+
+    fn find_workspace(&self, id: node::NodeID) -> Option<usize> {
+        self.workspaces.iter().position(|ws| ws.contains(id))
+    }
+
+    fn focus_next(&mut self) {
+        let current = self.current();
+        self.workspaces[current].focus_next();
+        self.arrange(current);
+    }
+
+    fn focus_prev(&mut self) {
+        let current = self.current();
+        self.workspaces[current].focus_prev();
+        self.arrange(current);
+    }
+
+    fn close_focused(&mut self) {
+        if let Some(win) = self.workspaces[self.current()].focused() {
+            self.close(win.id());
+        }
+    }
+
+    fn kill_focused(&mut self) {
+        if let Some(win) = self.workspaces[self.current()].focused() {
+            self.display.kill_client(win.id());
+        }
+    }
+
+    // Asks the window to close when it supports WM_DELETE_WINDOW and kills it otherwise.
+    // Windows that support _NET_WM_PING are pinged too, to notice when they hang.
+    fn close(&mut self, id: node::NodeID) {
+        if self.unresponsive.contains(&id) {
+            self.kill_process(id);
+            return;
+        }
+
+        let protocols = self.display.get_wm_protocols(id);
+        if !protocols.contains(&self.atoms.wm_delete_window) {
+            self.display.kill_client(id);
+            return;
+        }
+
+        self.send_protocol(id, self.atoms.wm_delete_window);
+        if protocols.contains(&self.atoms.net_wm_ping) {
+            self.send_protocol(id, self.atoms.net_wm_ping);
+            self.pings
+                .insert(id, Instant::now() + self.config.ping_timeout);
+        }
+    }
+
+    fn send_protocol(&self, id: node::NodeID, protocol: x::Atom) {
+        let data = [protocol as i64, xlib::CurrentTime as i64, id as i64, 0, 0];
         self.display
-            .configure_window(req.window, req.value_mask, &mut changes);
+            .send_client_message(id, self.atoms.wm_protocols, data);
+    }
+
+    // Windows whose ping timed out are reported, the next close kills them
+    fn check_pings(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<node::NodeID> = self
+            .pings
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            self.pings.remove(&id);
+            if self.find_workspace(id).is_some() && !self.unresponsive.contains(&id) {
+                let name = self.display.fetch_name(id).unwrap_or_default();
+                eprintln!(
+                    "Window {:#x} '{}' is not responding, close it again to kill it",
+                    id, name
+                );
+                self.unresponsive.push(id);
+            }
+        }
+    }
+
+    // Kills the process from _NET_WM_PID when it runs on this machine, otherwise only
+    // closes its connection to the X server
+    fn kill_process(&mut self, id: node::NodeID) {
+        let local = match (self.display.get_client_machine(id), hostname()) {
+            (Some(machine), Some(host)) => machine == host,
+            _ => false,
+        };
+        let pid = self
+            .display
+            .get_property(id, self.atoms.net_wm_pid, self.atoms.cardinal)
+            .first()
+            .map(|pid| *pid as libc::pid_t)
+            .filter(|pid| *pid > 0);
+
+        match pid {
+            Some(pid) if local => unsafe {
+                libc::kill(pid, libc::SIGKILL);
+            },
+            _ => self.display.kill_client(id),
+        }
+    }
+
+    fn spawn(&self, command: &str) {
+        let mut child = process::Command::new("sh");
+        child.arg("-c").arg(command);
+        // rwm ignores SIGCHLD to never wait for its children, which would carry over to
+        // programs that do wait for theirs
+        unsafe {
+            child.pre_exec(|| {
+                libc::signal(libc::SIGCHLD, libc::SIG_DFL);
+                Ok(())
+            });
+        }
+
+        if let Err(err) = child.spawn() {
+            eprintln!("Cannot spawn '{}': {}", command, err);
+        }
+    }
+
+    fn next_layout(&mut self) {
+        let current = self.current();
+        let workspace = &mut self.workspaces[current];
+        workspace.selected_layout = (workspace.selected_layout + 1) % self.layouts.len();
+        self.arrange(current);
+    }
+
+    fn prev_layout(&mut self) {
+        let current = self.current();
+        let workspace = &mut self.workspaces[current];
+        workspace.selected_layout = match workspace.selected_layout {
+            0 => self.layouts.len() - 1,
+            i => i - 1,
+        };
+        self.arrange(current);
+    }
+
+    fn resize_master(&mut self, delta: f32) {
+        let current = self.current();
+        self.workspaces[current].params.resize_master(delta);
+        self.arrange(current);
+    }
+
+    fn add_master(&mut self, delta: isize) {
+        let current = self.current();
+        self.workspaces[current].params.add_master(delta);
+        self.arrange(current);
+    }
+
+    fn resize_gaps(&mut self, inner: i32, outer: i32) {
+        let current = self.current();
+        self.workspaces[current].params.resize_gaps(inner, outer);
+        self.arrange(current);
+    }
+
+    fn set_gaps(&mut self, inner: u32, outer: u32) {
+        let current = self.current();
+        let gaps = &mut self.workspaces[current].params.gaps;
+        gaps.inner = inner;
+        gaps.outer = outer;
+        self.arrange(current);
+    }
+
+    fn toggle_smart_gaps(&mut self) {
+        let current = self.current();
+        let gaps = &mut self.workspaces[current].params.gaps;
+        gaps.smart = !gaps.smart;
+        self.arrange(current);
+    }
+
+    fn zoom(&mut self) {
+        let current = self.current();
+        self.workspaces[current].zoom();
+        self.arrange(current);
+    }
+
+    // The next window on the current workspace goes on that side of the focused one
+    fn preselect(&mut self, direction: Option<container::Direction>) {
+        let current = self.current();
+        self.workspaces[current].params.tree.preselect(direction);
+    }
+
+    fn resize_split(&mut self, delta: f32) {
+        self.update_tree(|tree, id| tree.resize(id, delta));
+    }
+
+    // Changes the tree around the focused window
+    fn update_tree<F: FnOnce(&mut container::Tree, node::NodeID)>(&mut self, f: F) {
+        let current = self.current();
+        let workspace = &mut self.workspaces[current];
+
+        if let Some(id) = workspace.focused().map(|win| win.id()) {
+            f(&mut workspace.params.tree, id);
+            self.arrange(current);
+        }
+    }
+
+    fn set_container_mode(&mut self, mode: container::Mode) -> server::Reply {
+        let mut changed = true;
+        self.update_tree(|tree, id| changed = tree.set_mode(id, mode));
+
+        if changed {
+            Ok(String::new())
+        } else {
+            Err("the focused window is not in a container".to_string())
+        }
+    }
+
+    // A workspace already shown on another monitor swaps places with the current one
+    fn switch_workspace(&mut self, i: usize) {
+        let current = self.current();
+        if i == current || i >= self.workspaces.len() {
+            return;
+        }
+
+        let previous: Vec<usize> = self.monitors.iter().map(|m| m.workspace).collect();
+        if let Some(other) = self.monitor_of(i) {
+            self.monitors[other].workspace = current;
+        }
+        self.monitors[self.focused_monitor].workspace = i;
+
+        self.update_visibility(&previous);
+        self.arrange(i);
+        self.arrange(current);
+    }
+
+    fn move_to_workspace(&mut self, i: usize, follow: bool) {
+        let current = self.current();
+        if i == current || i >= self.workspaces.len() {
+            return;
+        }
+
+        let id = match self.workspaces[current].focused() {
+            Some(win) => win.id(),
+            None => return,
+        };
+        self.send_to_workspace(id, current, i);
+
+        if follow {
+            match self.monitor_of(i) {
+                Some(m) => self.focus_monitor(m),
+                None => self.switch_workspace(i),
+            }
+        }
+
+        self.arrange(current);
+        self.arrange(i);
+    }
+
+    // Transients go along with their parent
+    fn send_to_workspace(&mut self, id: node::NodeID, from: usize, to: usize) {
+        if let Some(mut win) = self.workspaces[from].remove(id) {
+            self.translate_floating(win.as_mut(), from, to);
+            if self.monitor_of(to).is_none() {
+                win.hide();
+            }
+            self.workspaces[to].add(win);
+        }
+
+        let transients: Vec<node::NodeID> = self.workspaces[from]
+            .windows()
+            .iter()
+            .filter(|win| win.transient_for() == Some(id))
+            .map(|win| win.id())
+            .collect();
+        for transient in transients {
+            self.send_to_workspace(transient, from, to);
+        }
+    }
+
+    // Floating windows keep their place relative to the workspace area, unless they were
+    // dragged there already
+    fn translate_floating(&self, win: &mut dyn node::Node, from: usize, to: usize) {
+        if !win.is_floating() {
+            return;
+        }
+
+        if let (Some(from), Some(to)) = (self.workspaces[from].area, self.workspaces[to].area) {
+            let (position, size) = (win.position(), win.size());
+            let center = (
+                position.x + size.width as i32 / 2,
+                position.y + size.height as i32 / 2,
+            );
+            if from != to && to.contains(center.0, center.1) {
+                return;
+            }
+            win.set_position(position.x - from.x + to.x, position.y - from.y + to.y);
+        }
+    }
+
+    fn focus_monitor(&mut self, m: usize) {
+        if m < self.monitors.len() {
+            self.focused_monitor = m;
+        }
+    }
+
+    fn move_to_monitor(&mut self, m: usize) {
+        if m != self.focused_monitor && m < self.monitors.len() {
+            self.move_to_workspace(self.monitors[m].workspace, false);
+        }
+    }
+
+    // Monitors were plugged, unplugged or resized. Windows of workspaces whose monitor is
+    // gone move to the workspace of the focused monitor, so none of them end up hidden.
+    fn on_screen_change(&mut self) {
+        let rects = self.display.monitors();
+        let unchanged = rects
+            .iter()
+            .eq(self.monitors.iter().map(|monitor| &monitor.rect));
+        if rects.is_empty() || unchanged {
+            return;
+        }
+
+        if self.drag.take().is_some() {
+            self.display.ungrab_pointer();
+        }
+
+        let shown: Vec<usize> = self.monitors.iter().map(|m| m.workspace).collect();
+        let focused = self.workspaces[self.current()]
+            .focused()
+            .map(|win| win.id());
+        self.set_monitors(rects);
+
+        let current = self.current();
+        let target_focused = self.workspaces[current].focused().map(|win| win.id());
+        for i in shown {
+            if self.monitor_of(i).is_some() {
+                continue;
+            }
+            let ids: Vec<node::NodeID> = self.workspaces[i]
+                .windows()
+                .iter()
+                .map(|win| win.id())
+                .collect();
+            for id in ids {
+                self.send_to_workspace(id, i, current);
+            }
+        }
+
+        // Focus stays on the window that had it, wherever it was shown
+        for id in [target_focused, focused].iter().flatten() {
+            self.workspaces[current].focus_id(*id);
+        }
+        self.arrange_all();
+    }
+
+    // Unmanaged windows get what they ask for. Managed ones are told where they are after
+    // floating ones are moved and resized as asked, tiled and fullscreen ones stay put.
+    fn on_configure_request(&mut self, req: event::ConfigureRequestEvent) {
+        let win = match self
+            .find_workspace(req.window)
+            .and_then(|i| self.workspaces[i].get_mut(req.window))
+        {
+            Some(win) => win,
+            None => {
+                let mut changes = window::WindowChanges {
+                    x: req.x,
+                    y: req.y,
+                    width: req.width,
+                    height: req.height,
+                    border_width: req.border_width,
+                    sibling: req.above,
+                    stack_mode: req.detail,
+                };
+                self.display
+                    .configure_window(req.window, req.value_mask, &mut changes);
+                return;
+            }
+        };
+
+        if win.is_floating() && !win.state().fullscreen {
+            let has = |flag: u16| req.value_mask & flag as u64 != 0;
+            let (position, size) = (win.position(), win.size());
+            let border = 2 * win.border_width();
+
+            let x = if has(xlib::CWX) { req.x } else { position.x };
+            let y = if has(xlib::CWY) { req.y } else { position.y };
+            let width = if has(xlib::CWWidth) {
+                req.width.max(1) as u32 + border
+            } else {
+                size.width
+            };
+            let height = if has(xlib::CWHeight) {
+                req.height.max(1) as u32 + border
+            } else {
+                size.height
+            };
+
+            win.set_position(x, y);
+            win.set_size(width, height);
+        }
+
+        win.configure_notify();
     }
 
     fn on_map_request(&mut self, req: event::MapRequestEvent) {
         let win_id = req.window;
 
-        if let Some(win) = self.windows.iter().find(|win| win.id() == win_id) {
-            win.map();
+        // Iconified or hidden clients mapping themselves again want to be seen
+        if let Some(i) = self.find_workspace(win_id) {
+            self.activate(win_id, i);
             return;
         }
 
         if let Ok(attrs) = self.display.get_window_attributes(win_id) {
             if attrs.override_redirect > 0 || attrs.map_state != x::IS_VIEWABLE {
-                let win = window::Window::new(&self.display, win_id, attrs);
+                if self.map_unmanaged(win_id) {
+                    return;
+                }
+
+                let mut win = window::Window::new(
+                    &self.display,
+                    &self.atoms,
+                    win_id,
+                    attrs,
+                    self.config.style,
+                );
+                // Transients float on their parent's workspace
+                let parent = win.transient_for().and_then(|id| self.find_workspace(id));
+                if self.should_float(win_id) || parent.is_some() {
+                    win.set_floating(true);
+                }
+                win.set_state(self.initial_state(win_id));
+                let target = self.apply_rules(&mut win);
+                let target = parent.or(target).unwrap_or_else(|| self.current());
+                if win.is_floating() {
+                    self.place(&mut win, target);
+                }
                 win.map();
-                self.windows.push(Box::new(win));
+                if self.monitor_of(target).is_none() {
+                    win.hide();
+                }
+                if self.takes_focus(win_id) {
+                    self.workspaces[target].add(Box::new(win));
+                } else {
+                    self.workspaces[target].insert(Box::new(win));
+                }
+                self.arrange(target);
+            }
+        }
+    }
+
+    // Requests from pagers and tools like wmctrl or xdotool
+    fn on_client_message(&mut self, message: event::ClientMessageEvent) {
+        let atoms = self.atoms;
+        let kind = message.message_type;
+        let data = |i| message.data.get_long(i);
+        let win_id = message.window;
+
+        if kind == atoms.net_current_desktop {
+            self.switch_workspace(data(0) as usize);
+            return;
+        }
+
+        // Answer to a ping, sent back to the root window
+        if kind == atoms.wm_protocols && data(0) as x::Atom == atoms.net_wm_ping {
+            let id = data(2) as node::NodeID;
+            self.pings.remove(&id);
+            self.unresponsive.retain(|win| *win != id);
+            return;
+        }
+
+        let i = match self.find_workspace(win_id) {
+            Some(i) => i,
+            None => return,
+        };
+
+        if kind == atoms.net_active_window {
+            self.activate(win_id, i);
+        } else if kind == atoms.net_close_window {
+            self.close(win_id);
+        } else if kind == atoms.net_wm_desktop && data(0) as u32 as u64 == ALL_DESKTOPS {
+            self.change_state(win_id, i, STATE_ADD, [atoms.net_wm_state_sticky, 0]);
+        } else if kind == atoms.net_wm_desktop {
+            let to = data(0) as usize;
+            if to != i && to < self.workspaces.len() {
+                self.send_to_workspace(win_id, i, to);
+                self.arrange(i);
+                self.arrange(to);
             }
+        } else if kind == atoms.net_moveresize_window {
+            self.move_resize(win_id, i, data(0), [data(1), data(2), data(3), data(4)]);
+        } else if kind == atoms.net_wm_moveresize {
+            self.on_moveresize_request(win_id, i, data(0), data(1), data(2));
+        } else if kind == atoms.wm_change_state && data(0) == x::ICONIC_STATE as i64 {
+            self.change_state(win_id, i, STATE_ADD, [atoms.net_wm_state_hidden, 0]);
+        } else if kind == atoms.net_wm_state {
+            self.change_state(win_id, i, data(0), [data(1) as x::Atom, data(2) as x::Atom]);
         }
+    }
 
-        self.apply_selected_layout();
+    // _NET_WM_STATE, `action` removes, adds or toggles up to two states at once
+    fn change_state(&mut self, id: node::NodeID, i: usize, action: i64, properties: [x::Atom; 2]) {
+        let atoms = self.atoms;
+        let win = match self.workspaces[i].get_mut(id) {
+            Some(win) => win,
+            None => return,
+        };
+
+        let mut state = win.state();
+        for atom in properties.iter().filter(|atom| **atom != 0) {
+            if let Some(flag) = atoms.state_flag(&mut state, *atom) {
+                *flag = match action {
+                    STATE_REMOVE => false,
+                    STATE_ADD => true,
+                    STATE_TOGGLE => !*flag,
+                    _ => *flag,
+                };
+            }
+        }
+        win.set_state(state);
+
+        // Hidden windows give the focus away
+        let workspace = &mut self.workspaces[i];
+        if state.hidden && workspace.focused().is_some_and(|win| win.is(id)) {
+            workspace.focus_next();
+        }
+        self.arrange(i);
+    }
+
+    // Shows the window's workspace and focuses it
+    fn activate(&mut self, id: node::NodeID, i: usize) {
+        match self.monitor_of(i) {
+            Some(m) => self.focused_monitor = m,
+            None => self.switch_workspace(i),
+        }
+        if let Some(win) = self.workspaces[i].get_mut(id) {
+            let mut state = win.state();
+            state.hidden = false;
+            win.set_state(state);
+        }
+        self.workspaces[i].focus_id(id);
+        self.arrange(i);
+    }
+
+    // _NET_MOVERESIZE_WINDOW, bits 8 to 11 of `flags` tell which values are set.
+    // Tiled windows stay where the layout puts them.
+    fn move_resize(&mut self, id: node::NodeID, i: usize, flags: i64, values: [i64; 4]) {
+        let win = match self.workspaces[i].get_mut(id) {
+            Some(win) if win.is_floating() => win,
+            _ => return,
+        };
+
+        let (position, size) = (win.position(), win.size());
+        let border = 2 * win.border_width() as i64;
+        let value = |bit: usize, current: i64, extra: i64| {
+            if flags & (1 << (8 + bit)) != 0 {
+                values[bit] + extra
+            } else {
+                current
+            }
+        };
+
+        win.set_position(
+            value(0, position.x as i64, 0) as i32,
+            value(1, position.y as i64, 0) as i32,
+        );
+        win.set_size(
+            value(2, size.width as i64, border).max(1) as u32,
+            value(3, size.height as i64, border).max(1) as u32,
+        );
+    }
+
+    // _NET_WM_MOVERESIZE, a client decoration asks to be dragged like with the mouse bindings
+    fn on_moveresize_request(
+        &mut self,
+        id: node::NodeID,
+        i: usize,
+        x: i64,
+        y: i64,
+        direction: i64,
+    ) {
+        const MOVE: i64 = 8;
+        const MOVE_KEYBOARD: i64 = 10;
+        const CANCEL: i64 = 11;
+
+        if direction == CANCEL {
+            if self.drag.as_ref().is_some_and(|drag| drag.window == id) {
+                self.display.ungrab_pointer();
+                self.drag = None;
+            }
+            return;
+        }
+
+        let m = match self.monitor_of(i) {
+            Some(m) if self.drag.is_none() => m,
+            _ => return,
+        };
+        let kind = match direction {
+            MOVE | MOVE_KEYBOARD => mouse::DragKind::Move,
+            _ => mouse::DragKind::Resize,
+        };
+        let pointer = window::Position {
+            x: x as i32,
+            y: y as i32,
+        };
+        self.start_drag(m, id, kind, mouse::Grip::from_direction(direction), pointer);
+    }
+
+    // Docks, desktop windows and notifications are mapped as they are, outside of any
+    // workspace. Docks reserve space on the edges of the screen.
+    fn map_unmanaged(&mut self, id: node::NodeID) -> bool {
+        let types = self
+            .display
+            .get_property(id, self.atoms.net_wm_window_type, self.atoms.atom);
+
+        if types.contains(&self.atoms.net_wm_window_type_dock) {
+            self.display.select_events(id, xlib::PropertyChangeMask);
+            self.display.map_window(id);
+            self.docks.retain(|dock| dock.id != id);
+            self.docks.push(dock::Dock {
+                id,
+                strut: self.read_strut(id),
+            });
+            self.arrange_all();
+            true
+        } else if types.contains(&self.atoms.net_wm_window_type_desktop) {
+            self.display.map_window(id);
+            self.display.lower_window(id);
+            true
+        } else if types.contains(&self.atoms.net_wm_window_type_notification) {
+            // Shown above everything without a frame, and never focused
+            self.display.map_window(id);
+            self.display.raise_window(id);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_strut(&self, id: node::NodeID) -> dock::Strut {
+        [self.atoms.net_wm_strut_partial, self.atoms.net_wm_strut]
+            .iter()
+            .find_map(|property| {
+                let values = self
+                    .display
+                    .get_property(id, *property, self.atoms.cardinal);
+                dock::Strut::from_values(&values)
+            })
+            .unwrap_or_default()
+    }
+
+    // Clients change their size hints, panels resize or move the space they reserve
+    fn on_property_notify(&mut self, event: event::PropertyEvent) {
+        if event.atom == xlib::XA_WM_NORMAL_HINTS {
+            if let Some(i) = self.find_workspace(event.window) {
+                if let Some(win) = self.workspaces[i].get_mut(event.window) {
+                    win.update_size_hints();
+                }
+                self.arrange(i);
+            }
+            return;
+        }
+
+        let strut =
+            event.atom == self.atoms.net_wm_strut_partial || event.atom == self.atoms.net_wm_strut;
+        if !strut {
+            return;
+        }
+
+        let updated = self.read_strut(event.window);
+        if let Some(dock) = self.docks.iter_mut().find(|dock| dock.id == event.window) {
+            dock.strut = updated;
+            self.arrange_all();
+        }
+    }
+
+    // Clients grabbing the focus for themselves have it taken back
+    fn on_focus_in(&mut self, event: event::FocusChangeEvent) {
+        if self.input_focus.is_some_and(|id| id != event.window) {
+            self.input_focus = None;
+            self.update_focus();
+        }
     }
 
     fn on_unmap_notify(&mut self, req: event::UnmapEvent) {
-        if req.event == self.display.root() {
+        if let Some(i) = self.docks.iter().position(|dock| dock.id == req.window) {
+            self.docks.remove(i);
+            self.arrange_all();
+            return;
+        }
+
+        // Frames being unmapped are reported to the root window, the clients inside them to
+        // the frames. Clients withdrawing while hidden send a synthetic event to the root.
+        let synthetic = req.send_event != 0;
+        if req.event == self.display.root() && !synthetic {
             return;
         }
 
         let win_id = req.window;
 
-        if let Some((i, _)) = self
-            .windows
-            .iter_mut()
-            .enumerate()
-            .find(|(_, win)| win.id() == win_id)
-        {
-            self.windows.remove(i);
+        if let Some(i) = self.find_workspace(win_id) {
+            let expected = !synthetic
+                && self.workspaces[i]
+                    .get_mut(win_id)
+                    .is_some_and(|win| win.unmap_expected());
+            if !expected {
+                self.unmanage(win_id, i);
+            }
+        }
+    }
+
+    // Hidden clients are unmapped already, being destroyed is all they report
+    fn on_destroy_notify(&mut self, event: event::DestroyWindowEvent) {
+        let win_id = event.window;
+
+        if let Some(i) = self.docks.iter().position(|dock| dock.id == win_id) {
+            self.docks.remove(i);
+            self.arrange_all();
+            return;
         }
 
-        self.apply_selected_layout();
+        if let Some(i) = self.find_workspace(win_id) {
+            if let Some(win) = self.workspaces[i].get_mut(win_id) {
+                win.set_destroyed();
+            }
+            self.unmanage(win_id, i);
+        }
+    }
+
+    fn unmanage(&mut self, id: node::NodeID, i: usize) {
+        if self.drag.as_ref().is_some_and(|drag| drag.window == id) {
+            self.display.ungrab_pointer();
+            self.drag = None;
+        }
+
+        self.pings.remove(&id);
+        self.unresponsive.retain(|win| *win != id);
+
+        self.workspaces[i].remove(id);
+        self.arrange(i);
+    }
+
+    fn on_key_press(&mut self, event: event::KeyEvent) {
+        if let Some(action) = self.keys.find(&self.display, &event) {
+            if let Err(err) = self.handle(action.clone()) {
+                eprintln!("{}", err);
+            }
+        }
+    }
+
+    fn on_mapping_notify(&mut self, mut event: event::MappingEvent) {
+        self.display.refresh_keyboard_mapping(&mut event);
+
+        if event.request == xlib::MappingKeyboard || event.request == xlib::MappingModifier {
+            self.grab_events();
+        }
+    }
+
+    fn on_expose(&mut self, event: event::ExposeEvent) {
+        if event.count > 0 {
+            return;
+        }
+
+        let strip = self
+            .workspaces
+            .iter()
+            .flat_map(|workspace| workspace.tabs.iter())
+            .find(|strip| strip.id() == event.window);
+        if let Some(strip) = strip {
+            strip.draw();
+        }
+    }
+
+    // Clicking a tab focuses its window
+    fn on_tab_click(&mut self, event: &event::ButtonEvent) -> bool {
+        let found = self.monitors.iter().enumerate().find_map(|(m, monitor)| {
+            self.workspaces[monitor.workspace]
+                .tabs
+                .iter()
+                .find(|strip| strip.id() == event.window)
+                .map(|strip| (m, strip.tab_at(event.x, event.y)))
+        });
+        let (m, win_id) = match found {
+            Some(found) => found,
+            None => return false,
+        };
+
+        if let Some(win_id) = win_id {
+            self.focused_monitor = m;
+            let current = self.current();
+            self.workspaces[current].focus_id(win_id);
+            self.arrange(current);
+        }
+
+        true
+    }
+
+    fn on_button_press(&mut self, event: event::ButtonEvent) {
+        if self.drag.is_none() && self.on_tab_click(&event) {
+            return;
+        }
+
+        if self.drag.is_some() || event.subwindow == 0 {
+            return;
+        }
+
+        let kind = match self.buttons.find(&event) {
+            Some(kind) => kind,
+            None => return,
+        };
+
+        let found = self.monitors.iter().enumerate().find_map(|(m, monitor)| {
+            self.workspaces[monitor.workspace]
+                .windows()
+                .iter()
+                .find(|win| win.frame() == event.subwindow)
+                .map(|win| (m, win.id()))
+        });
+        let (m, win_id) = match found {
+            Some(found) => found,
+            None => return,
+        };
+
+        let pointer = window::Position {
+            x: event.x_root,
+            y: event.y_root,
+        };
+        self.start_drag(m, win_id, kind, mouse::Grip::BOTTOM_RIGHT, pointer);
+    }
+
+    // Dragging a window takes it out of the tiling layout
+    fn start_drag(
+        &mut self,
+        m: usize,
+        win_id: node::NodeID,
+        kind: mouse::DragKind,
+        grip: mouse::Grip,
+        pointer: window::Position,
+    ) {
+        let cursor = match kind {
+            mouse::DragKind::Move => self.move_cursor,
+            mouse::DragKind::Resize => self.resize_cursor,
+        };
+        let grabbed = self.display.grab_pointer(
+            self.display.root(),
+            (xlib::ButtonReleaseMask | xlib::PointerMotionMask) as u32,
+            cursor,
+        );
+        if !grabbed {
+            return;
+        }
+
+        self.focused_monitor = m;
+        let current = self.current();
+        let workspace = &mut self.workspaces[current];
+        workspace.focus_id(win_id);
+
+        let win = match workspace.focused_mut() {
+            Some(win) => win,
+            None => return,
+        };
+        // Starts from where it is, not from where it last floated
+        let retile = !win.is_floating();
+        let (position, size) = (win.position(), win.size());
+        win.set_floating(true);
+        if retile {
+            win.set_position(position.x, position.y);
+            win.set_size(size.width, size.height);
+        }
+
+        self.drag = Some(mouse::Drag::new(
+            win.id(),
+            kind,
+            grip,
+            pointer,
+            win.position(),
+            win.size(),
+        ));
+
+        if retile {
+            self.arrange(current);
+        } else {
+            self.restack(current);
+        }
+    }
+
+    fn on_motion_notify(&mut self, event: event::MotionEvent) {
+        let drag = match self.drag.as_ref() {
+            Some(drag) => drag,
+            None => return,
+        };
+
+        let motion = self.display.compress_motion(event);
+        let current = self.current();
+
+        if let Some(win) = self.workspaces[current].get_mut(drag.window) {
+            match drag.kind {
+                mouse::DragKind::Move => {
+                    let position = drag.position(&motion);
+                    win.set_position(position.x, position.y);
+                }
+                mouse::DragKind::Resize => {
+                    let size = drag.size(&motion);
+                    win.set_size(size.width, size.height);
+                    let position = drag.anchor(win.size());
+                    win.set_position(position.x, position.y);
+                }
+            }
+        }
+    }
+
+    // A window dropped on another monitor moves to the workspace shown there
+    fn on_button_release(&mut self, event: event::ButtonEvent) {
+        let drag = match self.drag.take() {
+            Some(drag) => drag,
+            None => return,
+        };
+        self.display.ungrab_pointer();
+
+        let current = self.current();
+        let target = match self.monitor_at(event.x_root, event.y_root) {
+            Some(m) if m != self.focused_monitor => self.monitors[m].workspace,
+            _ => return,
+        };
+
+        if self.workspaces[current].contains(drag.window) {
+            self.send_to_workspace(drag.window, current, target);
+            self.workspaces[target].focus_id(drag.window);
+            self.focused_monitor = self.monitor_of(target).unwrap_or(self.focused_monitor);
+            self.arrange(current);
+            self.arrange(target);
+        }
+    }
+}
+
+// Translate a floating window from one area to another and make sure it ends up visible
+fn keep_inside(win: &mut dyn node::Node, from: Option<window::Rect>, to: window::Rect) {
+    let mut position = win.position();
+    let size = win.size();
+
+    if let Some(from) = from.filter(|from| *from != to) {
+        position.x += to.x - from.x;
+        position.y += to.y - from.y;
+    }
+
+    let center = (
+        position.x + size.width as i32 / 2,
+        position.y + size.height as i32 / 2,
+    );
+    if !to.contains(center.0, center.1) {
+        position.x = to.x + (to.width as i32 - size.width as i32) / 2;
+        position.y = to.y + (to.height as i32 - size.height as i32) / 2;
+    }
+
+    let current = win.position();
+    if position.x != current.x || position.y != current.y {
+        win.set_position(position.x, position.y);
+    }
+}
+
+fn hostname() -> Option<String> {
+    let mut name = [0u8; 256];
+    if unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) } != 0 {
+        return None;
+    }
+    let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+    Some(String::from_utf8_lossy(&name[..len]).into_owned())
+}
+
+impl Drop for WindowManager {
+    fn drop(&mut self) {
+        self.display.free_cursor(self.move_cursor);
+        self.display.free_cursor(self.resize_cursor);
     }
 }
@@ -0,0 +1,215 @@
+use crate::core::{
+    container, layout,
+    node::{Node, NodeID},
+    tabs::TabStrip,
+    window::Rect,
+};
+
+pub struct Workspace {
+    name: String,
+
+    windows: Vec<Box<dyn Node>>,
+    focused: Option<usize>,
+
+    pub selected_layout: usize,
+    pub params: layout::Params,
+    pub tabs: Vec<TabStrip>,
+    // Windows from the top, as they were last restacked
+    pub stacking: Vec<NodeID>,
+
+    // Last area it was laid out in, floating windows are relative to it
+    pub area: Option<Rect>,
+}
+
+impl Workspace {
+    pub fn new(name: &str, params: layout::Params) -> Self {
+        Workspace {
+            name: name.to_string(),
+            windows: Vec::new(),
+            focused: None,
+            selected_layout: 0,
+            params,
+            tabs: Vec::new(),
+            stacking: Vec::new(),
+            area: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn windows(&self) -> &[Box<dyn Node>] {
+        &self.windows
+    }
+
+    pub fn windows_mut(&mut self) -> &mut [Box<dyn Node>] {
+        &mut self.windows
+    }
+
+    pub fn take_windows(&mut self) -> Vec<Box<dyn Node>> {
+        if let Some(win) = self.focused_mut() {
+            win.unfocus();
+        }
+        self.focused = None;
+        self.params.tree = container::Tree::default();
+        self.tabs.clear();
+
+        self.windows.drain(..).collect()
+    }
+
+    pub fn tiled(&self) -> Vec<NodeID> {
+        self.windows
+            .iter()
+            .filter(|win| win.is_tiled())
+            .map(|win| win.id())
+            .collect()
+    }
+
+    pub fn contains(&self, id: NodeID) -> bool {
+        self.windows.iter().any(|win| win.is(id))
+    }
+
+    pub fn get_mut(&mut self, id: NodeID) -> Option<&mut Box<dyn Node>> {
+        self.windows.iter_mut().find(|win| win.is(id))
+    }
+
+    pub fn focused(&self) -> Option<&dyn Node> {
+        self.focused.map(|i| self.windows[i].as_ref())
+    }
+
+    pub fn focused_mut(&mut self) -> Option<&mut Box<dyn Node>> {
+        self.focused.map(move |i| &mut self.windows[i])
+    }
+
+    // New windows split the focused one in the tree and get the focus
+    pub fn add(&mut self, win: Box<dyn Node>) {
+        self.insert(win);
+        self.focus(Some(self.windows.len() - 1));
+    }
+
+    // Same as `add`, the focus stays where it is
+    pub fn insert(&mut self, win: Box<dyn Node>) {
+        if win.is_tiled() {
+            let target = self
+                .focused()
+                .filter(|focused| focused.is_tiled())
+                .map(|focused| focused.id());
+            self.params
+                .tree
+                .insert(win.id(), target, self.area.as_ref());
+        }
+
+        self.windows.push(win);
+    }
+
+    pub fn remove(&mut self, id: NodeID) -> Option<Box<dyn Node>> {
+        let i = self.windows.iter().position(|win| win.is(id))?;
+        let mut win = self.windows.remove(i);
+        win.unfocus();
+        self.params.tree.remove(id);
+
+        let focused = match self.focused {
+            Some(f) if f > i => Some(f - 1),
+            Some(f) if f == i => Some(i.min(self.windows.len().saturating_sub(1))),
+            focused => focused,
+        };
+        self.focused = None;
+        self.focus(focused);
+
+        Some(win)
+    }
+
+    pub fn focus(&mut self, index: Option<usize>) {
+        if let Some(win) = self.focused.and_then(|i| self.windows.get_mut(i)) {
+            win.unfocus();
+        }
+
+        self.focused = index.filter(|i| *i < self.windows.len());
+
+        if let Some(win) = self.focused.and_then(|i| self.windows.get_mut(i)) {
+            win.focus();
+        }
+    }
+
+    pub fn focus_id(&mut self, id: NodeID) {
+        let index = self.windows.iter().position(|win| win.is(id));
+        if index.is_some() {
+            self.focus(index);
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        let len = self.windows.len();
+        let start = self.focused.map_or(0, |i| i + 1);
+        let next = (0..len)
+            .map(|n| (start + n) % len)
+            .find(|i| self.is_visible(*i));
+        if next.is_some() {
+            self.focus(next);
+        }
+    }
+
+    pub fn focus_prev(&mut self) {
+        let len = self.windows.len();
+        let start = self.focused.unwrap_or(0) + len;
+        let prev = (1..=len)
+            .map(|n| (start - n) % len)
+            .find(|i| self.is_visible(*i));
+        if prev.is_some() {
+            self.focus(prev);
+        }
+    }
+
+    // Hidden windows are skipped when cycling the focus
+    fn is_visible(&self, i: usize) -> bool {
+        !self.windows[i].state().hidden
+    }
+
+    // Moves the focused tiled window to the master position, or swaps the master with the
+    // next tiled window when it already is there
+    pub fn zoom(&mut self) {
+        let focused = match self.focused {
+            Some(i) if self.windows[i].is_tiled() => i,
+            _ => return,
+        };
+
+        let mut tiled = (0..self.windows.len()).filter(|i| self.windows[*i].is_tiled());
+        let master = tiled.next().unwrap_or(focused);
+        let promoted = if focused == master {
+            match tiled.next() {
+                Some(i) => i,
+                None => return,
+            }
+        } else {
+            focused
+        };
+
+        self.focus(None);
+        let win = self.windows.remove(promoted);
+        self.windows.insert(master, win);
+        self.focus(Some(master));
+    }
+
+    pub fn show(&self) {
+        for win in self.windows.iter().filter(|win| !win.state().hidden) {
+            win.show();
+        }
+        for strip in self.tabs.iter() {
+            strip.show();
+        }
+    }
+
+    pub fn hide(&self) {
+        for win in self.windows.iter() {
+            win.hide();
+        }
+        for strip in self.tabs.iter() {
+            strip.hide();
+        }
+    }
+}
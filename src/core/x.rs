@@ -1,17 +1,36 @@
-use std::{mem, ptr};
-use x11::xlib;
+use std::{
+    ffi, mem,
+    os::raw::{c_char, c_void},
+    ptr,
+};
+use x11::{keysym, xlib};
+use x11_dl::{xinerama, xrandr};
 
-use crate::core::{cursor, error, event, window};
+use crate::core::{cursor, error, event, key, window};
 
 pub type Bool = i32;
+pub type GC = xlib::GC;
+pub type Font = *mut xlib::XFontStruct;
+pub type Atom = xlib::Atom;
+pub type SizeHints = xlib::XSizeHints;
+pub type WmHints = xlib::XWMHints;
 pub const IS_VIEWABLE: i32 = xlib::IsViewable;
 
+// WM_STATE values, missing from the xlib bindings
+pub const WITHDRAWN_STATE: i32 = 0;
+pub const NORMAL_STATE: i32 = 1;
+pub const ICONIC_STATE: i32 = 3;
+
 pub struct Display {
     ptr: *mut xlib::Display,
     root: window::WindowID,
+
+    // Loaded at runtime, either can be missing
+    randr: Option<xrandr::Xrandr>,
+    randr_event_base: Option<i32>,
+    xinerama: Option<xinerama::Xlib>,
 }
 
-#[allow(dead_code)]
 impl Display {
     // Open display
     pub fn open() -> Result<Display, String> {
@@ -25,7 +44,13 @@ impl Display {
             // Get root window
             let root = xlib::XDefaultRootWindow(ptr);
 
-            let display = Display { ptr, root };
+            let mut display = Display {
+                ptr,
+                root,
+                randr: xrandr::Xrandr::open().ok(),
+                randr_event_base: None,
+                xinerama: xinerama::Xlib::open().ok(),
+            };
 
             // Global error handler
             xlib::XSetErrorHandler(Some(error::error_handler));
@@ -38,15 +63,31 @@ impl Display {
                 return Err("Another WM is running".to_string());
             }
 
+            display.randr_event_base = display.query_randr();
+
             Ok(display)
         }
     }
 
+    fn query_randr(&self) -> Option<i32> {
+        let randr = self.randr.as_ref()?;
+        unsafe {
+            let dpy = self.ptr as *mut x11_dl::xlib::Display;
+            let mut event_base = 0;
+            let mut error_base = 0;
+            if (randr.XRRQueryExtension)(dpy, &mut event_base, &mut error_base) == 0 {
+                return None;
+            }
+            Some(event_base)
+        }
+    }
+
     pub fn root(&self) -> window::WindowID {
         self.root
     }
 
     // Global
+    #[allow(clippy::too_many_arguments)]
     pub fn create_simple_window(
         &self,
         parent: window::WindowID,
@@ -89,6 +130,34 @@ impl Display {
         }
     }
 
+    // Replaces the events rwm listens to on the window
+    pub fn select_events(&self, w: window::WindowID, mask: i64) {
+        unsafe {
+            xlib::XSelectInput(self.ptr, w, mask);
+        }
+    }
+
+    // Root resizes and RandR output changes, reported as Event::ScreenChange
+    pub fn select_screen_changes(&self) {
+        unsafe {
+            xlib::XSelectInput(
+                self.ptr,
+                self.root,
+                xlib::SubstructureRedirectMask
+                    | xlib::SubstructureNotifyMask
+                    | xlib::StructureNotifyMask,
+            );
+
+            if let (Some(randr), Some(_)) = (self.randr.as_ref(), self.randr_event_base) {
+                (randr.XRRSelectInput)(
+                    self.ptr as *mut x11_dl::xlib::Display,
+                    self.root,
+                    xrandr::RRScreenChangeNotifyMask,
+                );
+            }
+        }
+    }
+
     pub fn sync(&self) {
         unsafe {
             xlib::XSync(self.ptr, 0);
@@ -102,9 +171,9 @@ impl Display {
         unsafe {
             xlib::XGrabServer(self.ptr);
 
-            let mut root_return = mem::MaybeUninit::uninit().assume_init();
-            let mut parent_return = mem::MaybeUninit::uninit().assume_init();
-            let mut w_ptr = mem::MaybeUninit::uninit().assume_init();
+            let mut root_return = 0;
+            let mut parent_return = 0;
+            let mut w_ptr = ptr::null_mut();
             let mut num = 0;
 
             if xlib::XQueryTree(
@@ -116,6 +185,7 @@ impl Display {
                 &mut num,
             ) == 0
             {
+                xlib::XUngrabServer(self.ptr);
                 return Err("XQueryTree failed".to_string());
             }
 
@@ -132,6 +202,123 @@ impl Display {
         }
     }
 
+    // Size of the default screen, known without a round trip
+    pub fn screen_rect(&self) -> window::Rect {
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.ptr);
+            window::Rect::new(
+                0,
+                0,
+                xlib::XDisplayWidth(self.ptr, screen) as u32,
+                xlib::XDisplayHeight(self.ptr, screen) as u32,
+            )
+        }
+    }
+
+    // Monitors, from RandR, Xinerama or the whole root window in that order
+    pub fn monitors(&self) -> Vec<window::Rect> {
+        let mut monitors = self.randr_monitors();
+        if monitors.is_empty() {
+            monitors = self.xinerama_monitors();
+        }
+        if monitors.is_empty() {
+            if let Ok(attrs) = self.get_window_attributes(self.root) {
+                monitors.push(window::Rect::new(
+                    0,
+                    0,
+                    attrs.width as u32,
+                    attrs.height as u32,
+                ));
+            }
+        }
+
+        // Mirrored outputs share the same area
+        let mut unique: Vec<window::Rect> = Vec::new();
+        for monitor in monitors {
+            if !unique.contains(&monitor) {
+                unique.push(monitor);
+            }
+        }
+        unique.sort_by_key(|monitor| (monitor.x, monitor.y));
+
+        unique
+    }
+
+    fn randr_monitors(&self) -> Vec<window::Rect> {
+        let randr = match self.randr.as_ref() {
+            Some(randr) => randr,
+            None => return Vec::new(),
+        };
+
+        let mut monitors = Vec::new();
+        if self.randr_event_base.is_none() {
+            return monitors;
+        }
+
+        unsafe {
+            let dpy = self.ptr as *mut x11_dl::xlib::Display;
+            let resources = (randr.XRRGetScreenResourcesCurrent)(dpy, self.root);
+            if resources.is_null() {
+                return monitors;
+            }
+
+            for i in 0..(*resources).ncrtc as isize {
+                let crtc = (randr.XRRGetCrtcInfo)(dpy, resources, *(*resources).crtcs.offset(i));
+                if crtc.is_null() {
+                    continue;
+                }
+
+                // Disabled CRTCs have no mode and no outputs
+                if (*crtc).mode != 0 && (*crtc).noutput > 0 && (*crtc).width > 0 {
+                    monitors.push(window::Rect::new(
+                        (*crtc).x,
+                        (*crtc).y,
+                        (*crtc).width,
+                        (*crtc).height,
+                    ));
+                }
+                (randr.XRRFreeCrtcInfo)(crtc);
+            }
+            (randr.XRRFreeScreenResources)(resources);
+        }
+
+        monitors
+    }
+
+    fn xinerama_monitors(&self) -> Vec<window::Rect> {
+        let xinerama = match self.xinerama.as_ref() {
+            Some(xinerama) => xinerama,
+            None => return Vec::new(),
+        };
+
+        let mut monitors = Vec::new();
+        unsafe {
+            let dpy = self.ptr as *mut x11_dl::xlib::Display;
+            if (xinerama.XineramaIsActive)(dpy) == 0 {
+                return monitors;
+            }
+
+            let mut num = 0;
+            let screens = (xinerama.XineramaQueryScreens)(dpy, &mut num);
+            if screens.is_null() {
+                return monitors;
+            }
+
+            for i in 0..num as isize {
+                let screen = &*screens.offset(i);
+                monitors.push(window::Rect::new(
+                    screen.x_org as i32,
+                    screen.y_org as i32,
+                    screen.width as u32,
+                    screen.height as u32,
+                ));
+            }
+            xlib::XFree(screens as *mut c_void);
+        }
+
+        monitors
+    }
+
     // Window configuration and move/resize
     pub fn configure_window(
         &self,
@@ -174,6 +361,26 @@ impl Display {
         }
     }
 
+    pub fn raise_window(&self, w: window::WindowID) {
+        unsafe {
+            xlib::XRaiseWindow(self.ptr, w);
+        }
+    }
+
+    pub fn lower_window(&self, w: window::WindowID) {
+        unsafe {
+            xlib::XLowerWindow(self.ptr, w);
+        }
+    }
+
+    // Top to bottom, the first window keeps its place
+    pub fn restack_windows(&self, windows: &[window::WindowID]) {
+        let mut windows = windows.to_vec();
+        unsafe {
+            xlib::XRestackWindows(self.ptr, windows.as_mut_ptr(), windows.len() as i32);
+        }
+    }
+
     pub fn unmap_window(&self, w: window::WindowID) {
         unsafe {
             xlib::XUnmapWindow(self.ptr, w);
@@ -186,12 +393,225 @@ impl Display {
         }
     }
 
+    pub fn get_class_hint(&self, w: window::WindowID) -> Option<(String, String)> {
+        unsafe {
+            let mut hint = xlib::XClassHint {
+                res_name: ptr::null_mut(),
+                res_class: ptr::null_mut(),
+            };
+            if xlib::XGetClassHint(self.ptr, w, &mut hint) == 0 {
+                return None;
+            }
+
+            let instance = take_string(hint.res_name);
+            let class = take_string(hint.res_class);
+            Some((instance, class))
+        }
+    }
+
+    pub fn fetch_name(&self, w: window::WindowID) -> Option<String> {
+        unsafe {
+            let mut name = ptr::null_mut();
+            if xlib::XFetchName(self.ptr, w, &mut name) == 0 || name.is_null() {
+                return None;
+            }
+            Some(take_string(name))
+        }
+    }
+
+    pub fn intern_atom(&self, name: &str) -> Atom {
+        let name = ffi::CString::new(name).unwrap_or_default();
+        unsafe { xlib::XInternAtom(self.ptr, name.as_ptr(), 0) }
+    }
+
+    // Values of a 32 bit property (atoms, windows, cardinals), empty when it isn't set
+    pub fn get_property(&self, w: window::WindowID, property: Atom, kind: Atom) -> Vec<u64> {
+        unsafe {
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut len = 0;
+            let mut bytes_after = 0;
+            let mut data = ptr::null_mut();
+
+            let status = xlib::XGetWindowProperty(
+                self.ptr,
+                w,
+                property,
+                0,
+                i64::MAX / 4,
+                0,
+                kind,
+                &mut actual_type,
+                &mut actual_format,
+                &mut len,
+                &mut bytes_after,
+                &mut data,
+            );
+            if status != xlib::Success as i32 || data.is_null() {
+                return Vec::new();
+            }
+
+            // Xlib hands 32 bit items back as longs
+            let values = if actual_format == 32 {
+                std::slice::from_raw_parts(data as *const u64, len as usize).to_vec()
+            } else {
+                Vec::new()
+            };
+            xlib::XFree(data as *mut c_void);
+            values
+        }
+    }
+
+    // Replaces a 32 bit property, Xlib wants the items as longs
+    pub fn set_property(&self, w: window::WindowID, property: Atom, kind: Atom, values: &[u64]) {
+        unsafe {
+            xlib::XChangeProperty(
+                self.ptr,
+                w,
+                property,
+                kind,
+                32,
+                xlib::PropModeReplace,
+                values.as_ptr() as *const u8,
+                values.len() as i32,
+            );
+        }
+    }
+
+    pub fn set_text_property(&self, w: window::WindowID, property: Atom, kind: Atom, text: &[u8]) {
+        unsafe {
+            xlib::XChangeProperty(
+                self.ptr,
+                w,
+                property,
+                kind,
+                8,
+                xlib::PropModeReplace,
+                text.as_ptr(),
+                text.len() as i32,
+            );
+        }
+    }
+
+    pub fn delete_property(&self, w: window::WindowID, property: Atom) {
+        unsafe {
+            xlib::XDeleteProperty(self.ptr, w, property);
+        }
+    }
+
+    pub fn get_wm_hints(&self, w: window::WindowID) -> Option<WmHints> {
+        unsafe {
+            let hints = xlib::XGetWMHints(self.ptr, w);
+            if hints.is_null() {
+                return None;
+            }
+            let copy = *hints;
+            xlib::XFree(hints as *mut c_void);
+            Some(copy)
+        }
+    }
+
+    // Focus goes back to whatever window is under the pointer if `w` disappears
+    pub fn set_input_focus(&self, w: window::WindowID) {
+        unsafe {
+            xlib::XSetInputFocus(self.ptr, w, xlib::RevertToPointerRoot, xlib::CurrentTime);
+        }
+    }
+
+    // Window a dialog belongs to
+    pub fn get_transient_for(&self, w: window::WindowID) -> Option<window::WindowID> {
+        unsafe {
+            let mut parent = 0;
+            if xlib::XGetTransientForHint(self.ptr, w, &mut parent) == 0 || parent == 0 {
+                return None;
+            }
+            Some(parent)
+        }
+    }
+
+    // Atoms listed in WM_PROTOCOLS
+    pub fn get_wm_protocols(&self, w: window::WindowID) -> Vec<Atom> {
+        unsafe {
+            let mut protocols = ptr::null_mut();
+            let mut count = 0;
+            if xlib::XGetWMProtocols(self.ptr, w, &mut protocols, &mut count) == 0
+                || protocols.is_null()
+            {
+                return Vec::new();
+            }
+            let atoms = std::slice::from_raw_parts(protocols, count as usize).to_vec();
+            xlib::XFree(protocols as *mut c_void);
+            atoms
+        }
+    }
+
+    // Host the client runs on, from WM_CLIENT_MACHINE
+    pub fn get_client_machine(&self, w: window::WindowID) -> Option<String> {
+        unsafe {
+            let mut text: xlib::XTextProperty = mem::zeroed();
+            if xlib::XGetWMClientMachine(self.ptr, w, &mut text) == 0 || text.value.is_null() {
+                return None;
+            }
+            let bytes = std::slice::from_raw_parts(text.value, text.nitems as usize);
+            let machine = String::from_utf8_lossy(bytes).into_owned();
+            xlib::XFree(text.value as *mut c_void);
+            Some(machine)
+        }
+    }
+
+    // Tells a reparented client its geometry relative to the root window
+    pub fn send_configure_notify(
+        &self,
+        w: window::WindowID,
+        rect: &window::Rect,
+        border_width: u32,
+    ) {
+        unsafe {
+            let mut event: xlib::XEvent = mem::zeroed();
+            event.configure.type_ = xlib::ConfigureNotify;
+            event.configure.event = w;
+            event.configure.window = w;
+            event.configure.x = rect.x;
+            event.configure.y = rect.y;
+            event.configure.width = rect.width as i32;
+            event.configure.height = rect.height as i32;
+            event.configure.border_width = border_width as i32;
+            xlib::XSendEvent(self.ptr, w, 0, xlib::StructureNotifyMask, &mut event);
+        }
+    }
+
+    // 32 bit ClientMessage sent to the window itself
+    pub fn send_client_message(&self, w: window::WindowID, kind: Atom, data: [i64; 5]) {
+        unsafe {
+            let mut event: xlib::XEvent = mem::zeroed();
+            event.client_message.type_ = xlib::ClientMessage;
+            event.client_message.window = w;
+            event.client_message.message_type = kind;
+            event.client_message.format = 32;
+            for (i, value) in data.iter().enumerate() {
+                event.client_message.data.set_long(i, *value);
+            }
+            xlib::XSendEvent(self.ptr, w, 0, xlib::NoEventMask, &mut event);
+        }
+    }
+
+    pub fn get_normal_hints(&self, w: window::WindowID) -> Option<SizeHints> {
+        unsafe {
+            let mut hints: SizeHints = mem::zeroed();
+            let mut supplied = 0;
+            if xlib::XGetWMNormalHints(self.ptr, w, &mut hints, &mut supplied) == 0 {
+                return None;
+            }
+            Some(hints)
+        }
+    }
+
     pub fn get_window_attributes(
         &self,
         w: window::WindowID,
     ) -> Result<window::WindowAttributes, String> {
         unsafe {
-            let mut attrs = mem::MaybeUninit::uninit().assume_init();
+            let mut attrs = mem::zeroed();
             if xlib::XGetWindowAttributes(self.ptr, w, &mut attrs) == 0 {
                 return Err("XGetWindowAttributes failed".to_string());
             }
@@ -228,12 +648,141 @@ impl Display {
         }
     }
 
+    pub fn set_window_border_width(&self, w: window::WindowID, width: u32) {
+        unsafe {
+            xlib::XSetWindowBorderWidth(self.ptr, w, width);
+        }
+    }
+
+    pub fn set_window_background(&self, w: window::WindowID, color: u64) {
+        unsafe {
+            xlib::XSetWindowBackground(self.ptr, w, color);
+            xlib::XClearWindow(self.ptr, w);
+        }
+    }
+
+    // Drawing
+    pub fn create_gc(&self, w: window::WindowID) -> GC {
+        unsafe { xlib::XCreateGC(self.ptr, w, 0, ptr::null_mut()) }
+    }
+
+    pub fn free_gc(&self, gc: GC) {
+        unsafe {
+            xlib::XFreeGC(self.ptr, gc);
+        }
+    }
+
+    pub fn set_foreground(&self, gc: GC, color: u64) {
+        unsafe {
+            xlib::XSetForeground(self.ptr, gc, color);
+        }
+    }
+
+    pub fn fill_rectangle(&self, w: window::WindowID, gc: GC, rect: &window::Rect) {
+        unsafe {
+            xlib::XFillRectangle(self.ptr, w, gc, rect.x, rect.y, rect.width, rect.height);
+        }
+    }
+
+    pub fn load_font(&self, name: &str) -> Option<Font> {
+        let name = ffi::CString::new(name).ok()?;
+        let font = unsafe { xlib::XLoadQueryFont(self.ptr, name.as_ptr()) };
+        if font.is_null() {
+            None
+        } else {
+            Some(font)
+        }
+    }
+
+    pub fn free_font(&self, font: Font) {
+        unsafe {
+            xlib::XFreeFont(self.ptr, font);
+        }
+    }
+
+    pub fn set_font(&self, gc: GC, font: Font) {
+        unsafe {
+            xlib::XSetFont(self.ptr, gc, (*font).fid);
+        }
+    }
+
+    // Height above and below the baseline
+    pub fn font_extents(&self, font: Font) -> (i32, i32) {
+        unsafe { ((*font).ascent, (*font).descent) }
+    }
+
+    pub fn text_width(&self, font: Font, text: &str) -> i32 {
+        unsafe { xlib::XTextWidth(font, text.as_ptr() as *const c_char, text.len() as i32) }
+    }
+
+    pub fn draw_string(&self, w: window::WindowID, gc: GC, x: i32, y: i32, text: &str) {
+        unsafe {
+            xlib::XDrawString(
+                self.ptr,
+                w,
+                gc,
+                x,
+                y,
+                text.as_ptr() as *const c_char,
+                text.len() as i32,
+            );
+        }
+    }
+
     pub fn ungrab_key(&self, keycode: i32, modifiers: u32, grab_window: window::WindowID) {
         unsafe {
             xlib::XUngrabKey(self.ptr, keycode, modifiers, grab_window);
         }
     }
 
+    pub fn kill_client(&self, w: window::WindowID) {
+        unsafe {
+            xlib::XKillClient(self.ptr, w);
+        }
+    }
+
+    // Keyboard
+    pub fn keysym_to_keycode(&self, keysym: key::KeySym) -> key::KeyCode {
+        unsafe { xlib::XKeysymToKeycode(self.ptr, keysym) }
+    }
+
+    pub fn keycode_to_keysym(&self, keycode: key::KeyCode) -> key::KeySym {
+        unsafe { xlib::XkbKeycodeToKeysym(self.ptr, keycode, 0, 0) }
+    }
+
+    // Modifier bit NumLock is bound to, it changes between keyboard layouts
+    pub fn numlock_mask(&self) -> u32 {
+        unsafe {
+            let modmap = xlib::XGetModifierMapping(self.ptr);
+            if modmap.is_null() {
+                return 0;
+            }
+
+            let numlock = self.keysym_to_keycode(keysym::XK_Num_Lock as key::KeySym);
+            let per_modifier = (*modmap).max_keypermod as isize;
+
+            let mut mask = 0;
+            for modifier in 0..8 {
+                for i in 0..per_modifier {
+                    let keycode = *(*modmap).modifiermap.offset(modifier * per_modifier + i);
+                    if keycode != 0 && keycode == numlock {
+                        mask = 1 << modifier;
+                    }
+                }
+            }
+            xlib::XFreeModifiermap(modmap);
+
+            mask
+        }
+    }
+
+    pub fn refresh_keyboard_mapping(&self, event: &mut event::MappingEvent) {
+        unsafe {
+            xlib::XRefreshKeyboardMapping(event);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn grab_button(
         &self,
         button: u32,
@@ -268,15 +817,114 @@ impl Display {
         }
     }
 
+    pub fn grab_pointer(
+        &self,
+        grab_window: window::WindowID,
+        event_mask: u32,
+        cursor: cursor::CursorID,
+    ) -> bool {
+        unsafe {
+            xlib::XGrabPointer(
+                self.ptr,
+                grab_window,
+                0,
+                event_mask,
+                xlib::GrabModeAsync,
+                xlib::GrabModeAsync,
+                0,
+                cursor,
+                xlib::CurrentTime,
+            ) == xlib::GrabSuccess
+        }
+    }
+
+    pub fn ungrab_pointer(&self) {
+        unsafe {
+            xlib::XUngrabPointer(self.ptr, xlib::CurrentTime);
+        }
+    }
+
+    pub fn create_font_cursor(&self, shape: u32) -> cursor::CursorID {
+        unsafe { xlib::XCreateFontCursor(self.ptr, shape) }
+    }
+
+    pub fn free_cursor(&self, cursor: cursor::CursorID) {
+        unsafe {
+            xlib::XFreeCursor(self.ptr, cursor);
+        }
+    }
+
+    // Drop every queued motion event but the last one
+    pub fn compress_motion(&self, mut motion: event::MotionEvent) -> event::MotionEvent {
+        unsafe {
+            let mut event: xlib::XEvent = mem::zeroed();
+            while xlib::XCheckTypedEvent(self.ptr, xlib::MotionNotify, &mut event) != 0 {
+                motion = event.motion;
+            }
+        }
+        motion
+    }
+
+    pub fn pending(&self) -> bool {
+        unsafe { xlib::XPending(self.ptr) > 0 }
+    }
+
+    // Like next_event but gives up after `timeout` milliseconds without events
+    pub fn wait_event(&self, timeout: i32) -> Option<event::Event> {
+        if !self.pending() {
+            let mut fd = libc::pollfd {
+                fd: unsafe { xlib::XConnectionNumber(self.ptr) },
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            unsafe {
+                libc::poll(&mut fd, 1, timeout);
+            }
+
+            if !self.pending() {
+                return None;
+            }
+        }
+
+        Some(self.next_event())
+    }
+
     pub fn next_event(&self) -> event::Event {
         unsafe {
-            let mut event: xlib::XEvent = mem::MaybeUninit::uninit().assume_init();
+            let mut event: xlib::XEvent = mem::zeroed();
             xlib::XNextEvent(self.ptr, &mut event);
+
+            if let (Some(randr), Some(base)) = (self.randr.as_ref(), self.randr_event_base) {
+                if event.get_type() == base + xrandr::RRScreenChangeNotify {
+                    (randr.XRRUpdateConfiguration)(&mut event as *mut xlib::XEvent as *mut _);
+                    return event::Event::ScreenChange;
+                }
+            }
+
             event::Event::from(event)
         }
     }
 }
 
+pub fn string_to_keysym(name: &str) -> Option<key::KeySym> {
+    let name = ffi::CString::new(name).ok()?;
+    match unsafe { xlib::XStringToKeysym(name.as_ptr()) } {
+        0 => None,
+        keysym => Some(keysym),
+    }
+}
+
+// Copy a string allocated by Xlib and free it
+unsafe fn take_string(s: *mut c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+
+    let string = ffi::CStr::from_ptr(s).to_string_lossy().into_owned();
+    xlib::XFree(s as *mut c_void);
+    string
+}
+
 impl Drop for Display {
     fn drop(&mut self) {
         unsafe {
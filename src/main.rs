@@ -1,18 +1,54 @@
 mod core;
 
-use crate::core::{window_manager::WindowManager, x::Display};
+use std::{env, sync::mpsc};
+
+use crate::core::{
+    config::Config,
+    server::{self, Server},
+    window_manager::WindowManager,
+    x::Display,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
+    // `rwm msg <action>` talks to the running instance
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("msg") {
+        let response = server::send(&args[1..].join(" "))?;
+        println!("{}", response);
+        return Ok(());
+    }
+
+    // Spawned programs are never waited for, let the kernel reap them
+    unsafe {
+        libc::signal(libc::SIGCHLD, libc::SIG_IGN);
+    }
+
     let display = Display::open()?;
 
-    let mut wm = WindowManager::new(display);
+    let config = Config::load();
+
+    let mut wm = WindowManager::new(display, config);
     println!("[RWM]");
 
+    let (tx, rx) = mpsc::channel();
+    let server = Server::new(tx.clone());
+    println!("- Listening on {}", Server::path().display());
+    tokio::spawn(async move {
+        if let Err(err) = server.start().await {
+            eprintln!("IPC server stopped: {}", err);
+        }
+    });
+    tokio::spawn(async move {
+        if let Err(err) = server::watch_signals(tx).await {
+            eprintln!("Cannot watch signals: {}", err);
+        }
+    });
+
     let windows = wm.scan()?;
     println!("- Attached to {} windows.", windows);
     println!("- Running...");
-    wm.run()?;
+    wm.run(rx)?;
 
     Ok(())
 }